@@ -0,0 +1,153 @@
+//! Arc-length reparameterization of splines.
+//!
+//! Splines are sampled with time. Depending on how keys are laid out in time and space, a value
+//! travels faster or slower along the curve. An `ArcLengthTable` maps distances travelled along a
+//! spline to times so that paths can be traversed at constant speed.
+
+use cgmath::InnerSpace;
+
+use anim::spline::{Interpolate, Spline, Time};
+
+/// Table mapping distances travelled along a spline to times.
+#[derive(Clone, Debug)]
+pub struct ArcLengthTable {
+  // (time, distance) pairs, sorted by both time and distance
+  samples: Vec<(Time, f32)>
+}
+
+impl ArcLengthTable {
+  /// Build an arc-length table out of a spline.
+  ///
+  /// Each segment of the spline is subdivided `precision` times. Parts of the spline that cannot be
+  /// sampled (e.g. the first and last segments of `Interpolation::CatmullRom` splines) don’t
+  /// account in the length of the path.
+  pub fn new<T>(spline: &Spline<T>, precision: usize) -> Self where T: Interpolate + InnerSpace<Scalar = f32> {
+    let keys = spline.keys();
+    let precision = precision.max(1);
+    let mut samples = Vec::new();
+    let mut prev: Option<T> = None;
+    let mut dist = 0.;
+
+    if keys.len() == 1 {
+      samples.push((keys[0].t, 0.));
+    }
+
+    for i in 0..keys.len().saturating_sub(1) {
+      let t0 = keys[i].t;
+      let t1 = keys[i + 1].t;
+      let dt = (t1 - t0) / precision as Time;
+
+      // empty segments have no length
+      if dt <= 0. {
+        continue;
+      }
+
+      for k in 0..precision + 1 {
+        // the end of the segment is sampled on the segment itself, as the next one might not be
+        // sampleable
+        let t = if k == precision { t1 } else { t0 + dt * k as Time };
+
+        // the start of a segment was already sampled as the end of the previous one
+        if k == 0 && prev.is_some() && samples.last().map_or(false, |&(st, _)| st == t) {
+          continue;
+        }
+
+        match spline.sample_in_segment(i, t) {
+          Some(p) => {
+            if let Some(q) = prev {
+              dist += (p - q).magnitude();
+            }
+
+            samples.push((t, dist));
+            prev = Some(p);
+          },
+          None => prev = None
+        }
+      }
+    }
+
+    ArcLengthTable {
+      samples: samples
+    }
+  }
+
+  /// Total length of the path.
+  pub fn length(&self) -> f32 {
+    self.samples.last().map_or(0., |&(_, d)| d)
+  }
+
+  /// Time at which a given distance is travelled along the path.
+  ///
+  /// # Return
+  ///
+  /// `None` if the distance is negative or greater than the length of the path.
+  pub fn time_at_distance(&self, d: f32) -> Option<Time> {
+    let samples = &self.samples;
+
+    if samples.is_empty() || d < 0. || d > self.length() {
+      return None;
+    }
+
+    let i = lower_bound(samples, |&(_, sd)| sd < d);
+
+    if i == 0 {
+      return Some(samples[0].0);
+    }
+
+    let (t0, d0) = samples[i - 1];
+    let (t1, d1) = samples[i];
+
+    Some(t0 + (t1 - t0) * (d - d0) / (d1 - d0))
+  }
+
+  /// Distance travelled along the path at a given time.
+  ///
+  /// # Return
+  ///
+  /// `None` if the time is out of the spline’s keys’ range.
+  pub fn distance_at_time(&self, t: Time) -> Option<f32> {
+    let samples = &self.samples;
+
+    match (samples.first(), samples.last()) {
+      (Some(&(first, _)), Some(&(last, _))) if first <= t && t <= last => (),
+      _ => return None
+    }
+
+    let i = lower_bound(samples, |&(st, _)| st < t);
+
+    if i == 0 {
+      return Some(samples[0].1);
+    }
+
+    let (t0, d0) = samples[i - 1];
+    let (t1, d1) = samples[i];
+
+    Some(d0 + (d1 - d0) * (t - t0) / (t1 - t0))
+  }
+
+  /// Sample a spline at a given distance along its path.
+  ///
+  /// The spline must be the one used to build the table.
+  pub fn sample_at_distance<T>(&self, spline: &Spline<T>, d: f32) -> Option<T> where T: Interpolate {
+    self.time_at_distance(d).and_then(|t| spline.sample_inclusive(t))
+  }
+}
+
+// Index of the first sample for which the predicate doesn’t hold anymore. The predicate must be
+// true for a prefix of the samples and false for the rest.
+fn lower_bound<F>(samples: &[(Time, f32)], pred: F) -> usize where F: Fn(&(Time, f32)) -> bool {
+  let mut lo = 0;
+  let mut hi = samples.len();
+
+  while lo < hi {
+    let mid = (lo + hi) / 2;
+
+    if pred(&samples[mid]) {
+      lo = mid + 1;
+    } else {
+      hi = mid;
+    }
+  }
+
+  lo
+}
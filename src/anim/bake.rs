@@ -0,0 +1,211 @@
+//! Spline baking.
+//!
+//! A baked spline is a spline sampled at a fixed rate into a flat array of floats. Such an array
+//! can be uploaded to the GPU – as a 1D texture or a uniform array – or sampled on the CPU, trading
+//! memory for sampling cost.
+
+use std::marker::PhantomData;
+
+use anim::spline::{Interpolate, Spline, Time};
+use linear::{Quat, Scale, V2, V3, V4};
+
+/// Types which values can be flattened to floats.
+pub trait Bake: Sized {
+  /// Number of floats (channels) a value is made of.
+  fn channels() -> usize;
+  /// Write the channels of a value.
+  fn write_channels(&self, out: &mut Vec<f32>);
+  /// Read a value back from its channels. `data` is exactly `Self::channels()` long.
+  fn read_channels(data: &[f32]) -> Self;
+}
+
+impl Bake for f32 {
+  fn channels() -> usize { 1 }
+
+  fn write_channels(&self, out: &mut Vec<f32>) {
+    out.push(*self);
+  }
+
+  fn read_channels(data: &[f32]) -> Self {
+    data[0]
+  }
+}
+
+impl Bake for V2<f32> {
+  fn channels() -> usize { 2 }
+
+  fn write_channels(&self, out: &mut Vec<f32>) {
+    out.extend_from_slice(&[self.x, self.y]);
+  }
+
+  fn read_channels(data: &[f32]) -> Self {
+    V2::new(data[0], data[1])
+  }
+}
+
+impl Bake for V3<f32> {
+  fn channels() -> usize { 3 }
+
+  fn write_channels(&self, out: &mut Vec<f32>) {
+    out.extend_from_slice(&[self.x, self.y, self.z]);
+  }
+
+  fn read_channels(data: &[f32]) -> Self {
+    V3::new(data[0], data[1], data[2])
+  }
+}
+
+impl Bake for V4<f32> {
+  fn channels() -> usize { 4 }
+
+  fn write_channels(&self, out: &mut Vec<f32>) {
+    out.extend_from_slice(&[self.x, self.y, self.z, self.w]);
+  }
+
+  fn read_channels(data: &[f32]) -> Self {
+    V4::new(data[0], data[1], data[2], data[3])
+  }
+}
+
+/// Quaternions are laid out as *x*, *y*, *z*, *w*, *w* being the scalar part.
+impl Bake for Quat<f32> {
+  fn channels() -> usize { 4 }
+
+  fn write_channels(&self, out: &mut Vec<f32>) {
+    out.extend_from_slice(&[self.v.x, self.v.y, self.v.z, self.s]);
+  }
+
+  fn read_channels(data: &[f32]) -> Self {
+    Quat::new(data[3], data[0], data[1], data[2])
+  }
+}
+
+impl Bake for Scale {
+  fn channels() -> usize { 3 }
+
+  fn write_channels(&self, out: &mut Vec<f32>) {
+    out.extend_from_slice(&[self.x, self.y, self.z]);
+  }
+
+  fn read_channels(data: &[f32]) -> Self {
+    Scale::new(data[0], data[1], data[2])
+  }
+}
+
+/// Layout of a baked spline.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Layout {
+  /// Number of floats per sample.
+  pub channels: usize,
+  /// Number of samples.
+  pub samples: usize,
+  /// Time of the first sample.
+  pub start: Time,
+  /// Number of samples per unit of time.
+  pub rate: f32
+}
+
+/// A spline baked at a fixed rate.
+///
+/// Samples are stored interleaved: the channels of the first sample come first, then the ones of
+/// the second sample, etc.
+#[derive(Clone, Debug)]
+pub struct BakedSpline<T> {
+  layout: Layout,
+  data: Vec<f32>,
+  _t: PhantomData<T>
+}
+
+impl<T> BakedSpline<T> where T: Bake {
+  /// Bake a spline between two times, with `rate` samples per unit of time.
+  ///
+  /// Times at which the spline cannot be sampled hold the value of the closest key if they’re
+  /// out of the keys’ range, or the previous sample otherwise.
+  ///
+  /// # Panic
+  ///
+  /// This function panics if the rate is not strictly positive or if `end < start`.
+  pub fn new(spline: &Spline<T>, start: Time, end: Time, rate: f32) -> Self where T: Interpolate {
+    assert!(rate > 0.);
+    assert!(start <= end);
+
+    let samples = ((end - start) * rate).floor() as usize + 1;
+    let channels = T::channels();
+    let keys = spline.keys();
+    let mut data = Vec::with_capacity(samples * channels);
+    let mut prev = None;
+
+    for i in 0..samples {
+      let t = start + i as Time / rate;
+
+      let value = spline.sample(t).or_else(|| {
+        match (keys.first(), keys.last()) {
+          (Some(first), _) if t <= first.t => Some(first.value),
+          (_, Some(last)) if t >= last.t => Some(last.value),
+          _ => prev
+        }
+      });
+
+      // if there’s nothing to hold yet, use the first key; a spline without keys bakes to zeros
+      match value.or_else(|| keys.first().map(|key| key.value)) {
+        Some(value) => value.write_channels(&mut data),
+        None => data.extend((0..channels).map(|_| 0.))
+      }
+
+      prev = value;
+    }
+
+    BakedSpline {
+      layout: Layout {
+        channels: channels,
+        samples: samples,
+        start: start,
+        rate: rate
+      },
+      data: data,
+      _t: PhantomData
+    }
+  }
+
+  /// Layout of the baked data.
+  pub fn layout(&self) -> Layout {
+    self.layout
+  }
+
+  /// Baked data.
+  pub fn data(&self) -> &[f32] {
+    &self.data
+  }
+
+  /// Get a baked sample.
+  pub fn get(&self, i: usize) -> Option<T> {
+    let channels = self.layout.channels;
+
+    if i < self.layout.samples {
+      Some(T::read_channels(&self.data[i * channels .. (i + 1) * channels]))
+    } else {
+      None
+    }
+  }
+
+  /// Sample the baked spline at a given time, interpolating linearly between samples.
+  ///
+  /// # Return
+  ///
+  /// `None` if the time is out of the baked range.
+  pub fn sample(&self, t: Time) -> Option<T> where T: Interpolate {
+    let pos = (t - self.layout.start) * self.layout.rate;
+    let last = (self.layout.samples - 1) as f32;
+
+    if pos < 0. || pos > last {
+      return None;
+    }
+
+    let i = pos.floor() as usize;
+
+    match (self.get(i), self.get(i + 1)) {
+      (Some(a), Some(b)) => Some(T::lerp(a, b, pos - i as f32)),
+      (a, _) => a
+    }
+  }
+}
@@ -0,0 +1,183 @@
+//! Spline blending.
+//!
+//! A `Blend` combines the samples of several splines – its *layers* – into a single value. Every
+//! layer has a weight, which is itself a spline so that it can be animated, and a mode:
+//!
+//!   - an *override* layer moves the value blended so far towards its own sample, by its weight;
+//!     to crossfade between two takes, keep the bottom one at a constant weight of 1 and move the
+//!     weight of the top one from 0 to 1;
+//!   - an *additive* layer applies its sample as a delta on top of the value blended so far,
+//!     scaled by its weight; it’s typically used to layer a secondary motion on top of a main
+//!     one.
+//!
+//! Layers are applied in order, starting from the identity value of the type – see `Blendable`.
+//! Weights are not normalized: as every layer blends with the result of the layers below it, two
+//! override layers which weights go from 1 to 0 and from 0 to 1 don’t crossfade. Halfway, the
+//! bottom layer only moves halfway from the identity, so the result is a quarter of the bottom
+//! take and half of the top one.
+
+use anim::spline::{Interpolate, Spline, Time};
+use linear::{Quat, Scale, V2, V3, V4};
+
+/// Types which values can be blended in layers.
+pub trait Blendable: Interpolate {
+  /// Value a blend starts from, which is also the neutral delta of additive layers.
+  fn identity() -> Self;
+  /// Apply a delta to a value.
+  fn accumulate(self, delta: Self) -> Self;
+}
+
+impl Blendable for f32 {
+  fn identity() -> Self { 0. }
+
+  fn accumulate(self, delta: Self) -> Self {
+    self + delta
+  }
+}
+
+impl Blendable for V2<f32> {
+  fn identity() -> Self { V2::new(0., 0.) }
+
+  fn accumulate(self, delta: Self) -> Self {
+    self + delta
+  }
+}
+
+impl Blendable for V3<f32> {
+  fn identity() -> Self { V3::new(0., 0., 0.) }
+
+  fn accumulate(self, delta: Self) -> Self {
+    self + delta
+  }
+}
+
+impl Blendable for V4<f32> {
+  fn identity() -> Self { V4::new(0., 0., 0., 0.) }
+
+  fn accumulate(self, delta: Self) -> Self {
+    self + delta
+  }
+}
+
+/// Deltas are rotations, applied in the local frame of the value. Weighting a delta slerps it from
+/// the identity rotation.
+impl Blendable for Quat<f32> {
+  fn identity() -> Self { Quat::new(1., 0., 0., 0.) }
+
+  fn accumulate(self, delta: Self) -> Self {
+    self * delta
+  }
+}
+
+/// Deltas are scale factors.
+impl Blendable for Scale {
+  fn identity() -> Self { Scale::default() }
+
+  fn accumulate(self, delta: Self) -> Self {
+    self * delta
+  }
+}
+
+/// How a layer combines with the layers below it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BlendMode {
+  /// Interpolate from the value blended so far to the layer’s sample. The weight is clamped to
+  /// [0, 1].
+  Override,
+  /// Apply the layer’s sample as a delta. The weight is not clamped, so that a delta can be
+  /// exaggerated.
+  Additive
+}
+
+/// Layer of a blend.
+#[derive(Clone, Debug)]
+pub struct Layer<T> {
+  pub spline: Spline<T>,
+  /// Weight of the layer. It’s held before its first key and after its last one unless it has
+  /// extrapolation modes, and in segments that can’t be sampled – e.g. the first and last ones with
+  /// `Interpolation::CatmullRom` – the nearest sampleable value is held. An empty weight spline
+  /// stands for a constant weight of 1, and a weight spline that can’t be sampled at all for a
+  /// weight of 0.
+  pub weight: Spline<f32>,
+  pub mode: BlendMode
+}
+
+impl<T> Layer<T> {
+  pub fn new(spline: Spline<T>, weight: Spline<f32>, mode: BlendMode) -> Self {
+    Layer {
+      spline: spline,
+      weight: weight,
+      mode: mode
+    }
+  }
+
+  /// Sample the weight of the layer at a given time.
+  pub fn sample_weight(&self, t: Time) -> f32 {
+    if self.weight.keys().is_empty() {
+      1.
+    } else {
+      self.weight.try_clamped_sample(t).unwrap_or(0.)
+    }
+  }
+}
+
+/// Weighted blend of several splines.
+#[derive(Clone, Debug)]
+pub struct Blend<T> {
+  layers: Vec<Layer<T>>
+}
+
+impl<T> Blend<T> {
+  pub fn new(layers: Vec<Layer<T>>) -> Self {
+    Blend {
+      layers: layers
+    }
+  }
+
+  /// Layers of the blend, from the bottom one to the top one.
+  pub fn layers(&self) -> &[Layer<T>] {
+    &self.layers
+  }
+
+  /// Add a layer on top of the others.
+  pub fn push(&mut self, layer: Layer<T>) {
+    self.layers.push(layer);
+  }
+
+  /// Remove the layer at the given index.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `index` is out of bounds.
+  pub fn remove(&mut self, index: usize) -> Layer<T> {
+    self.layers.remove(index)
+  }
+
+  /// Sample the blend at a given time.
+  ///
+  /// Layers which spline cannot be sampled at `t` are skipped.
+  ///
+  /// # Return
+  ///
+  /// `None` if no layer can be sampled at `t`.
+  pub fn sample(&self, t: Time) -> Option<T> where T: Blendable {
+    let mut blended = None;
+
+    for layer in &self.layers {
+      let sample = match layer.spline.sample(t) {
+        Some(sample) => sample,
+        None => continue
+      };
+
+      let acc = blended.unwrap_or_else(T::identity);
+      let weight = layer.sample_weight(t);
+
+      blended = Some(match layer.mode {
+        BlendMode::Override => Interpolate::lerp(acc, sample, weight.max(0.).min(1.)),
+        BlendMode::Additive => acc.accumulate(Interpolate::lerp(T::identity(), sample, weight))
+      });
+    }
+
+    blended
+  }
+}
@@ -0,0 +1,278 @@
+//! Animation clips.
+//!
+//! An animation clip gathers several named splines – called *channels* – of possibly different
+//! types in a single resource. It’s typically used to hold every animated parameter of a shot:
+//!
+//! ```json
+//! {
+//!   "camera.position": { "type": "v3", "spline": [ … ] },
+//!   "camera.fov": { "type": "f32", "spline": [ … ] },
+//!   "light0.color": { "type": "v3", "spline": { "keys": [ … ], "post": "repeat" } }
+//! }
+//! ```
+//!
+//! The `spline` field of a channel follows the same format as spline files.
+
+use serde_json::{from_reader, to_writer_pretty};
+use std::collections::HashMap;
+use std::collections::hash_map;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use anim::spline::{Interpolate, Spline, SplineManifest, Time};
+use linear::{Quat, Scale, V2, V3, V4};
+use sys::resource::{CacheKey, Load, LoadError, LoadResult, Save, SaveError, Store, StoreKey};
+
+/// A typed spline living in an animation clip.
+#[derive(Clone, Debug)]
+pub enum Channel {
+  /// Scalar channel, `"f32"` on disk.
+  F32(Spline<f32>),
+  /// 2D vector channel, `"v2"` on disk.
+  V2(Spline<V2<f32>>),
+  /// 3D vector channel, `"v3"` on disk.
+  V3(Spline<V3<f32>>),
+  /// 4D vector channel, `"v4"` on disk.
+  V4(Spline<V4<f32>>),
+  /// Orientation channel, `"quat"` on disk.
+  Quat(Spline<Quat<f32>>),
+  /// Scale channel, `"scale"` on disk.
+  Scale(Spline<Scale>)
+}
+
+impl From<Spline<f32>> for Channel {
+  fn from(spline: Spline<f32>) -> Self {
+    Channel::F32(spline)
+  }
+}
+
+impl From<Spline<V2<f32>>> for Channel {
+  fn from(spline: Spline<V2<f32>>) -> Self {
+    Channel::V2(spline)
+  }
+}
+
+impl From<Spline<V3<f32>>> for Channel {
+  fn from(spline: Spline<V3<f32>>) -> Self {
+    Channel::V3(spline)
+  }
+}
+
+impl From<Spline<V4<f32>>> for Channel {
+  fn from(spline: Spline<V4<f32>>) -> Self {
+    Channel::V4(spline)
+  }
+}
+
+impl From<Spline<Quat<f32>>> for Channel {
+  fn from(spline: Spline<Quat<f32>>) -> Self {
+    Channel::Quat(spline)
+  }
+}
+
+impl From<Spline<Scale>> for Channel {
+  fn from(spline: Spline<Scale>) -> Self {
+    Channel::Scale(spline)
+  }
+}
+
+/// Types of values that can be animated by a `Channel`.
+///
+/// It’s what lets `AnimationClip::spline` and `AnimationClip::sample` pick the spline of a channel
+/// by the type of its values; there’s an implementation for every variant of `Channel`.
+pub trait ChannelValue: Sized {
+  /// Get the spline of a channel if it animates this type.
+  fn from_channel(channel: &Channel) -> Option<&Spline<Self>>;
+}
+
+impl ChannelValue for f32 {
+  fn from_channel(channel: &Channel) -> Option<&Spline<Self>> {
+    match *channel {
+      Channel::F32(ref spline) => Some(spline),
+      _ => None
+    }
+  }
+}
+
+impl ChannelValue for V2<f32> {
+  fn from_channel(channel: &Channel) -> Option<&Spline<Self>> {
+    match *channel {
+      Channel::V2(ref spline) => Some(spline),
+      _ => None
+    }
+  }
+}
+
+impl ChannelValue for V3<f32> {
+  fn from_channel(channel: &Channel) -> Option<&Spline<Self>> {
+    match *channel {
+      Channel::V3(ref spline) => Some(spline),
+      _ => None
+    }
+  }
+}
+
+impl ChannelValue for V4<f32> {
+  fn from_channel(channel: &Channel) -> Option<&Spline<Self>> {
+    match *channel {
+      Channel::V4(ref spline) => Some(spline),
+      _ => None
+    }
+  }
+}
+
+impl ChannelValue for Quat<f32> {
+  fn from_channel(channel: &Channel) -> Option<&Spline<Self>> {
+    match *channel {
+      Channel::Quat(ref spline) => Some(spline),
+      _ => None
+    }
+  }
+}
+
+impl ChannelValue for Scale {
+  fn from_channel(channel: &Channel) -> Option<&Spline<Self>> {
+    match *channel {
+      Channel::Scale(ref spline) => Some(spline),
+      _ => None
+    }
+  }
+}
+
+/// Animation clip, gathering named channels.
+#[derive(Clone, Debug)]
+pub struct AnimationClip {
+  channels: HashMap<String, Channel>
+}
+
+impl AnimationClip {
+  /// Create an empty animation clip.
+  pub fn new() -> Self {
+    AnimationClip {
+      channels: HashMap::new()
+    }
+  }
+
+  /// Add a channel to the clip. If a channel already exists with the same name, it’s replaced and
+  /// returned.
+  pub fn insert<C>(&mut self, name: &str, channel: C) -> Option<Channel> where C: Into<Channel> {
+    self.channels.insert(name.to_owned(), channel.into())
+  }
+
+  /// Remove a channel from the clip.
+  pub fn remove(&mut self, name: &str) -> Option<Channel> {
+    self.channels.remove(name)
+  }
+
+  /// Get a channel by its name.
+  pub fn channel(&self, name: &str) -> Option<&Channel> {
+    self.channels.get(name)
+  }
+
+  /// Iterate over all the channels.
+  pub fn channels(&self) -> hash_map::Iter<String, Channel> {
+    self.channels.iter()
+  }
+
+  /// Get the spline of a channel by its name.
+  ///
+  /// # Return
+  ///
+  /// `None` if there’s no such channel or if it doesn’t animate values of type `T`.
+  pub fn spline<T>(&self, name: &str) -> Option<&Spline<T>> where T: ChannelValue {
+    self.channels.get(name).and_then(T::from_channel)
+  }
+
+  /// Sample a channel at a given time.
+  ///
+  /// # Return
+  ///
+  /// `None` if there’s no such channel, if it doesn’t animate values of type `T` or if its spline
+  /// cannot be sampled at that time.
+  pub fn sample<T>(&self, name: &str, t: Time) -> Option<T> where T: ChannelValue + Interpolate {
+    self.spline(name).and_then(|spline: &Spline<T>| spline.sample(t))
+  }
+}
+
+/// Channel as written on disk.
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "type", content = "spline")]
+enum ChannelManifest {
+  #[serde(rename = "f32")]
+  F32(SplineManifest<f32>),
+  #[serde(rename = "v2")]
+  V2(SplineManifest<[f32; 2]>),
+  #[serde(rename = "v3")]
+  V3(SplineManifest<[f32; 3]>),
+  #[serde(rename = "v4")]
+  V4(SplineManifest<[f32; 4]>),
+  #[serde(rename = "quat")]
+  Quat(SplineManifest<[f32; 4]>),
+  #[serde(rename = "scale")]
+  Scale(SplineManifest<[f32; 3]>)
+}
+
+impl From<ChannelManifest> for Channel {
+  fn from(manifest: ChannelManifest) -> Self {
+    match manifest {
+      ChannelManifest::F32(spline) => Channel::F32(Spline::from_manifest(spline)),
+      ChannelManifest::V2(spline) => Channel::V2(Spline::from_manifest(spline)),
+      ChannelManifest::V3(spline) => Channel::V3(Spline::from_manifest(spline)),
+      ChannelManifest::V4(spline) => Channel::V4(Spline::from_manifest(spline)),
+      ChannelManifest::Quat(spline) => Channel::Quat(Spline::from_manifest(spline)),
+      ChannelManifest::Scale(spline) => Channel::Scale(Spline::from_manifest(spline))
+    }
+  }
+}
+
+impl<'a> From<&'a Channel> for ChannelManifest {
+  fn from(channel: &'a Channel) -> Self {
+    match *channel {
+      Channel::F32(ref spline) => ChannelManifest::F32(spline.to_manifest()),
+      Channel::V2(ref spline) => ChannelManifest::V2(spline.to_manifest()),
+      Channel::V3(ref spline) => ChannelManifest::V3(spline.to_manifest()),
+      Channel::V4(ref spline) => ChannelManifest::V4(spline.to_manifest()),
+      Channel::Quat(ref spline) => ChannelManifest::Quat(spline.to_manifest()),
+      Channel::Scale(ref spline) => ChannelManifest::Scale(spline.to_manifest())
+    }
+  }
+}
+
+/// Key of an animation clip in the store: the path of its JSON file.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct AnimationClipKey(pub String);
+
+impl CacheKey for AnimationClipKey {
+  type Target = AnimationClip;
+}
+
+impl StoreKey for AnimationClipKey {
+  fn key_to_path(&self) -> PathBuf {
+    self.0.clone().into()
+  }
+}
+
+impl Load for AnimationClip {
+  fn load<P>(path: P, _: &mut Store) -> Result<LoadResult<Self>, LoadError> where P: AsRef<Path> {
+    let path = path.as_ref();
+
+    let file = File::open(path).map_err(|_| LoadError::FileNotFound(path.to_path_buf()))?;
+    let manifest: HashMap<String, ChannelManifest> = from_reader(file).map_err(|e| LoadError::ParseFailed(format!("{:?}", e)))?;
+
+    let clip = AnimationClip {
+      channels: manifest.into_iter().map(|(name, channel)| (name, channel.into())).collect()
+    };
+
+    Ok(clip.into())
+  }
+}
+
+impl Save for AnimationClip {
+  fn save<P>(&self, path: P) -> Result<(), SaveError> where P: AsRef<Path> {
+    let path = path.as_ref();
+    let manifest: HashMap<&String, ChannelManifest> = self.channels.iter().map(|(name, channel)| (name, channel.into())).collect();
+
+    let file = File::create(path).map_err(|_| SaveError::CannotCreate(path.to_path_buf()))?;
+    to_writer_pretty(file, &manifest).map_err(|e| SaveError::SerializationFailed(format!("{:?}", e)))
+  }
+}
@@ -0,0 +1,404 @@
+//! GLSL code generation.
+//!
+//! Splines can be evaluated on the GPU by turning them into GLSL functions. For instance, a
+//! `Spline<f32>` named `fade` yields a function like:
+//!
+//! ```glsl
+//! float fade(float t) { … }
+//! ```
+//!
+//! The generated code uses the same interpolation and extrapolation math as `Spline::sample`. The
+//! helper functions it needs are emitted along with it and prefixed with its name, so that several
+//! curves can live in the same shader module. Sampling a generated function where `Spline::sample`
+//! would fail – outside of the keys’ range without extrapolation – holds the first or last key.
+//!
+//! The generated code can be written to a `.spsl` file to be imported by other shader modules, or
+//! parsed directly into a `Module` with `spline_to_module`.
+
+use std::f32::consts;
+
+use anim::easing::{BACK_C1, BACK_C3, BOUNCE_N1, BOUNCE_PARABOLAS, ELASTIC_C4, Family, Flavour};
+use anim::spline::{Extrapolation, Handle, Interpolate, Interpolation, Key, Spline, SplineError, Time};
+use linear::{V2, V3, V4};
+use render::shader::lang::parser;
+use render::shader::module::Module;
+
+/// Types which values can be written as GLSL literals.
+pub trait GlslValue: Interpolate {
+  /// GLSL type of the values (e.g. `vec3`).
+  fn glsl_type() -> &'static str;
+  /// GLSL literal representing the value.
+  fn to_glsl(&self) -> String;
+  /// Whether all the components of the value are finite; GLSL has no literal for NaN and
+  /// infinities.
+  fn is_finite(&self) -> bool;
+}
+
+impl GlslValue for f32 {
+  fn glsl_type() -> &'static str { "float" }
+
+  fn to_glsl(&self) -> String {
+    float(*self)
+  }
+
+  fn is_finite(&self) -> bool {
+    f32::is_finite(*self)
+  }
+}
+
+impl GlslValue for V2<f32> {
+  fn glsl_type() -> &'static str { "vec2" }
+
+  fn to_glsl(&self) -> String {
+    format!("vec2({}, {})", float(self.x), float(self.y))
+  }
+
+  fn is_finite(&self) -> bool {
+    self.x.is_finite() && self.y.is_finite()
+  }
+}
+
+impl GlslValue for V3<f32> {
+  fn glsl_type() -> &'static str { "vec3" }
+
+  fn to_glsl(&self) -> String {
+    format!("vec3({}, {}, {})", float(self.x), float(self.y), float(self.z))
+  }
+
+  fn is_finite(&self) -> bool {
+    self.x.is_finite() && self.y.is_finite() && self.z.is_finite()
+  }
+}
+
+impl GlslValue for V4<f32> {
+  fn glsl_type() -> &'static str { "vec4" }
+
+  fn to_glsl(&self) -> String {
+    format!("vec4({}, {}, {}, {})", float(self.x), float(self.y), float(self.z), float(self.w))
+  }
+
+  fn is_finite(&self) -> bool {
+    self.x.is_finite() && self.y.is_finite() && self.z.is_finite() && self.w.is_finite()
+  }
+}
+
+/// Error that might occur while generating code.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CodegenError {
+  /// The spline has no key.
+  NoKey,
+  /// The spline cannot be sampled everywhere in its keys’ range; see `Spline::validate`.
+  InvalidSpline(SplineError),
+  /// The time, value, handles or parameters of the key at the given index are not all finite.
+  NonFiniteKey(usize),
+  /// The generated code couldn’t be parsed as a shader module.
+  ParseFailed(String)
+}
+
+/// Generate a GLSL function named `name` evaluating a spline.
+pub fn spline_to_glsl<T>(spline: &Spline<T>, name: &str) -> Result<String, CodegenError> where T: GlslValue {
+  let keys = spline.keys();
+  let ty = T::glsl_type();
+
+  if keys.is_empty() {
+    return Err(CodegenError::NoKey);
+  }
+
+  spline.validate().map_err(CodegenError::InvalidSpline)?;
+
+  if let Some(i) = keys.iter().position(|key| !is_finite_key(key)) {
+    return Err(CodegenError::NonFiniteKey(i));
+  }
+
+  if keys.len() == 1 {
+    return Ok(format!("{} {}(float t) {{\n  return {};\n}}\n", ty, name, keys[0].value.to_glsl()));
+  }
+
+  let mut helpers = Helpers::default();
+
+  // function sampling the keys’ range, looking up segments linearly
+  let mut closed = format!("{} {}_closed(float t) {{\n", ty, name);
+  let last_segment = keys.len() - 2;
+
+  for i in 0..last_segment + 1 {
+    let cp0 = &keys[i];
+    let cp1 = &keys[i+1];
+    let dt = cp1.t - cp0.t;
+
+    if dt <= 0. {
+      // empty segments are never sampled, unless they’re the last one
+      if i == last_segment {
+        closed += &format!("  return {};\n", cp1.value.to_glsl());
+      }
+
+      continue;
+    }
+
+    let expr = segment(spline, i, name, &mut helpers);
+    let nt = format!("float nt = (t - {}) / {};", float(cp0.t), float(dt));
+
+    if i < last_segment {
+      closed += &format!("  if (t < {}) {{\n    {}\n    return {};\n  }}\n\n", float(cp1.t), nt, expr);
+    } else {
+      closed += &format!("  {}\n  return {};\n", nt, expr);
+    }
+  }
+
+  closed += "}\n";
+
+  let first = &keys[0];
+  let last = &keys[keys.len() - 1];
+  let pre = extrapolation(spline, spline.pre_extrapolation(), first, true, name);
+  let post = extrapolation(spline, spline.post_extrapolation(), last, false, name);
+
+  let main = format!("{} {}(float t) {{\n  if (t < {}) {{\n{}  }}\n\n  if (t >= {}) {{\n{}  }}\n\n  return {}_closed(t);\n}}\n",
+                     ty, name, float(first.t), pre, float(last.t), post, name);
+
+  Ok(helpers.to_glsl(ty, name) + &closed + "\n" + &main)
+}
+
+/// Generate a shader module holding a GLSL function named `name` evaluating a spline.
+///
+/// See `spline_to_glsl` for further details.
+pub fn spline_to_module<T>(spline: &Spline<T>, name: &str) -> Result<Module, CodegenError> where T: GlslValue {
+  let src = spline_to_glsl(spline, name)?;
+
+  match parser::parse_str(&src[..], parser::module) {
+    parser::ParseResult::Ok(module) => Ok(Module(module)),
+    parser::ParseResult::Err(e) => Err(CodegenError::ParseFailed(format!("{:?}", e))),
+    _ => Err(CodegenError::ParseFailed("incomplete input".to_owned()))
+  }
+}
+
+// Whether everything written out of a key is finite.
+fn is_finite_key<T>(key: &Key<T>) -> bool where T: GlslValue {
+  let handle_finite = |handle: Option<Handle<T>>| handle.map_or(true, |h| h.t.is_finite() && h.value.is_finite());
+  let params_finite = match key.interpolation {
+    Interpolation::Step(threshold) => threshold.is_finite(),
+    Interpolation::KochanekBartels { tension, continuity, bias } => {
+      tension.is_finite() && continuity.is_finite() && bias.is_finite()
+    },
+    _ => true
+  };
+
+  key.t.is_finite() && key.value.is_finite() && handle_finite(key.in_handle) && handle_finite(key.out_handle) &&
+    params_finite
+}
+
+// GLSL float literal. The number must be finite.
+fn float(x: f32) -> String {
+  let s = format!("{}", x);
+
+  if s.contains('.') || s.contains('e') {
+    s
+  } else {
+    s + ".0"
+  }
+}
+
+// Expression sampling the segment starting at the key i, given its normalized time nt and the
+// time t.
+fn segment<T>(spline: &Spline<T>, i: usize, name: &str, helpers: &mut Helpers) -> String where T: GlslValue {
+  let keys = spline.keys();
+  let cp0 = &keys[i];
+  let cp1 = &keys[i+1];
+  let a = cp0.value.to_glsl();
+  let b = cp1.value.to_glsl();
+
+  match cp0.interpolation {
+    Interpolation::Step(threshold) => format!("(nt < {} ? {} : {})", float(threshold), a, b),
+    Interpolation::Linear => format!("mix({}, {}, nt)", a, b),
+    Interpolation::Cosine => format!("mix({}, {}, (1.0 - cos(nt * {})) * 0.5)", a, b, float(consts::PI)),
+    Interpolation::CatmullRom => {
+      // same tangents as cubic_hermite; neighbours are checked by Spline::validate
+      let cpm0 = &keys[i-1];
+      let cpm1 = &keys[i+2];
+      let m0 = (cp1.value - cpm0.value) / (cp1.t - cpm0.t);
+      let m1 = (cpm1.value - cp0.value) / (cpm1.t - cp0.t);
+
+      helpers.hermite = true;
+      format!("{}_hermite({}, {}, {}, {}, nt)", name, a, m0.to_glsl(), b, m1.to_glsl())
+    },
+    Interpolation::Bezier => {
+      // same handles as Spline::sample
+      let clamp = |t: Time| t.max(cp0.t).min(cp1.t);
+      let h0 = cp0.out_handle.map_or((cp0.t, cp0.value), |h| (clamp(h.t), h.value));
+      let h1 = cp1.in_handle.map_or((cp1.t, cp1.value), |h| (clamp(h.t), h.value));
+
+      helpers.bezier = true;
+      format!("{}_bezier({}, {}, {}, {}, {}_bezier_time({}, {}, {}, {}, t))",
+              name, a, h0.1.to_glsl(), h1.1.to_glsl(), b,
+              name, float(cp0.t), float(h0.0), float(h1.0), float(cp1.t))
+    },
+    Interpolation::KochanekBartels { .. } | Interpolation::MonotoneCubic => {
+      // neighbours are checked by Spline::validate
+      let (m0, m1) = spline.hermite_tangents(i).unwrap();
+
+      helpers.hermite = true;
+      format!("{}_hermite({}, {}, {}, {}, nt)", name, a, m0.to_glsl(), b, m1.to_glsl())
+    },
+    Interpolation::Ease(easing) => {
+      let (family, flavour) = easing.split();
+      let f = format!("{}_{}_in", name, family_name(family));
+
+      if !helpers.families.contains(&family) {
+        helpers.families.push(family);
+      }
+
+      let eased = match flavour {
+        Flavour::In => format!("{}(nt)", f),
+        Flavour::Out => format!("1.0 - {}(1.0 - nt)", f),
+        Flavour::InOut => format!("(nt < 0.5 ? {f}(2.0 * nt) * 0.5 : 1.0 - {f}(2.0 - 2.0 * nt) * 0.5)", f = f)
+      };
+
+      format!("mix({}, {}, {})", a, b, eased)
+    }
+  }
+}
+
+// Statements sampling a spline outside of its keys’ range, on the side of the boundary key.
+fn extrapolation<T>(spline: &Spline<T>, extrapolation: Option<Extrapolation>, boundary: &Key<T>, pre: bool, name: &str) -> String
+    where T: GlslValue {
+  let keys = spline.keys();
+  let first = &keys[0];
+  let last = &keys[keys.len() - 1];
+  let period = float(last.t - first.t);
+  let cycle = format!("    float c = floor((t - {}) / {});\n", float(first.t), period);
+
+  // every mode behaves as constant when all the keys are at the same time, as in Spline::sample
+  let extrapolation = if last.t > first.t { extrapolation } else { None };
+
+  match extrapolation {
+    None | Some(Extrapolation::Constant) => format!("    return {};\n", boundary.value.to_glsl()),
+    Some(Extrapolation::Linear) => {
+      // the derivative the spline extrapolates with
+      let outside = if pre { first.t - 1. } else { last.t + 1. };
+
+      match spline.sample_derivative(outside) {
+        Some(d) => format!("    return {} + {} * (t - {});\n", boundary.value.to_glsl(), d.to_glsl(), float(boundary.t)),
+        None => format!("    return {};\n", boundary.value.to_glsl())
+      }
+    },
+    Some(Extrapolation::Repeat) => {
+      format!("{}    return {}_closed(t - c * {});\n", cycle, name, period)
+    },
+    Some(Extrapolation::PingPong) => {
+      format!("{}    float l = t - {} - c * {};\n    return mod(c, 2.0) == 0.0 ? {n}_closed({} + l) : {n}_closed({} - l);\n",
+              cycle, float(first.t), period, float(first.t), float(last.t), n = name)
+    },
+    Some(Extrapolation::OffsetRepeat) => {
+      format!("{}    return {}_closed(t - c * {}) + ({} - {}) * c;\n",
+              cycle, name, period, last.value.to_glsl(), first.value.to_glsl())
+    }
+  }
+}
+
+// Helper functions a generated curve relies on.
+#[derive(Default)]
+struct Helpers {
+  hermite: bool,
+  bezier: bool,
+  families: Vec<Family>
+}
+
+impl Helpers {
+  fn to_glsl(&self, ty: &str, name: &str) -> String {
+    let mut glsl = String::new();
+
+    if self.hermite {
+      glsl += &format!("{ty} {n}_hermite({ty} a, {ty} m0, {ty} b, {ty} m1, float t) {{
+  float t2 = t * t;
+  float t3 = t2 * t;
+  return a * (2.0 * t3 - 3.0 * t2 + 1.0) + m0 * (t3 - 2.0 * t2 + t) + b * (-2.0 * t3 + 3.0 * t2) + m1 * (t3 - t2);
+}}
+
+", ty = ty, n = name);
+    }
+
+    if self.bezier {
+      glsl += &format!("{ty} {n}_bezier({ty} p0, {ty} p1, {ty} p2, {ty} p3, float u) {{
+  {ty} a = mix(p0, p1, u);
+  {ty} b = mix(p1, p2, u);
+  {ty} c = mix(p2, p3, u);
+  return mix(mix(a, b, u), mix(b, c, u), u);
+}}
+
+float {n}_bezier_time(float t0, float t1, float t2, float t3, float t) {{
+  float lo = 0.0;
+  float hi = 1.0;
+  float u = clamp((t - t0) / (t3 - t0), 0.0, 1.0);
+
+  for (int i = 0; i < 32; ++i) {{
+    float iu = 1.0 - u;
+    float x = iu * iu * iu * t0 + 3.0 * iu * iu * u * t1 + 3.0 * iu * u * u * t2 + u * u * u * t3;
+    float dx = 3.0 * iu * iu * (t1 - t0) + 6.0 * iu * u * (t2 - t1) + 3.0 * u * u * (t3 - t2);
+    float err = x - t;
+
+    if (abs(err) < 0.000001) {{
+      break;
+    }}
+
+    if (err > 0.0) {{
+      hi = u;
+    }} else {{
+      lo = u;
+    }}
+
+    float next = u - err / dx;
+    u = abs(dx) > 0.000001 && next > lo && next < hi ? next : (lo + hi) * 0.5;
+  }}
+
+  return u;
+}}
+
+", ty = ty, n = name);
+    }
+
+    for &family in &self.families {
+      glsl += &format!("float {}_{}_in(float x) {{\n{}}}\n\n", name, family_name(family), ease_in(family));
+    }
+
+    glsl
+  }
+}
+
+// Name of an easing family in helper functions.
+fn family_name(family: Family) -> &'static str {
+  match family {
+    Family::Quad => "quad",
+    Family::Cubic => "cubic",
+    Family::Expo => "expo",
+    Family::Back => "back",
+    Family::Elastic => "elastic",
+    Family::Bounce => "bounce"
+  }
+}
+
+// Body of the ease-in function of an easing family, taking x as input; same math and constants
+// as Easing::ease.
+fn ease_in(family: Family) -> String {
+  match family {
+    Family::Quad => "  return x * x;\n".to_owned(),
+    Family::Cubic => "  return x * x * x;\n".to_owned(),
+    Family::Expo => "  return x <= 0.0 ? 0.0 : exp2(10.0 * x - 10.0);\n".to_owned(),
+    Family::Back => format!("  return {} * x * x * x - {} * x * x;\n", float(BACK_C3), float(BACK_C1)),
+    Family::Elastic => {
+      format!("  return x <= 0.0 ? 0.0 : (x >= 1.0 ? 1.0 : -exp2(10.0 * x - 10.0) * sin((10.0 * x - 10.75) * {}));\n",
+              float(ELASTIC_C4))
+    },
+    Family::Bounce => {
+      // mirrored bouncing ease-out; the parabolas are looked up from the last one, the first one
+      // being the fallback
+      let mut glsl = "  float u = 1.0 - x;\n  float c = 0.0;\n  float o = 0.0;\n\n  ".to_owned();
+      let (first, others) = BOUNCE_PARABOLAS.split_last().unwrap();
+
+      for &(start, center, offset) in others {
+        glsl += &format!("if (u >= {}) {{\n    c = {};\n    o = {};\n  }} else ", float(start), float(center), float(offset));
+      }
+
+      glsl += &format!("{{\n    c = {};\n    o = {};\n  }}\n\n  u -= c;\n  return 1.0 - ({} * u * u + o);\n",
+                       float(first.1), float(first.2), float(BOUNCE_N1));
+      glsl
+    }
+  }
+}
@@ -0,0 +1,102 @@
+//! Discrete splines.
+//!
+//! Splines require their values to be interpolable, which rules out booleans, integers, enums or
+//! strings. A `DiscreteSpline` only holds values from key to key, so that scene switches, effect
+//! toggles or captions can be keyframed on the same timeline – and with the same file format – as
+//! continuous splines.
+
+use serde_json::{from_reader, to_writer_pretty};
+use std::fs::File;
+use std::path::Path;
+
+use anim::spline::{Interpolation, Key, Spline, SplineDeserializerAdapter, SplineManifest,
+                   SplineResourceKey, SplineSerializerAdapter, Time, upper_bound};
+use sys::resource::{Load, LoadError, LoadResult, Save, SaveError, Store};
+
+/// Spline holding values from key to key.
+///
+/// The interpolation mode of keys is ignored, except for `Interpolation::Step`, which threshold is
+/// honored. Bézier handles and extrapolation modes are ignored as well.
+#[derive(Clone, Debug)]
+pub struct DiscreteSpline<T> {
+  keys: Vec<Key<T>>
+}
+
+impl<T> DiscreteSpline<T> {
+  /// Create a new discrete spline out of keys. The keys don’t have to be sorted because they’re
+  /// sorted by this function.
+  pub fn from_keys(mut keys: Vec<Key<T>>) -> Self {
+    keys.sort_by(|k0, k1| k0.t.partial_cmp(&k1.t).unwrap());
+
+    DiscreteSpline {
+      keys: keys
+    }
+  }
+
+  /// Keys of the spline, sorted by time.
+  pub fn keys(&self) -> &[Key<T>] {
+    &self.keys
+  }
+
+  /// Sample the spline at a given time.
+  ///
+  /// Unlike `Spline::sample`, the last key’s value is held forever.
+  ///
+  /// # Return
+  ///
+  /// `None` if you sample before the first key.
+  pub fn sample(&self, t: Time) -> Option<&T> {
+    let keys = &self.keys;
+    let lo = upper_bound(keys, t);
+
+    if lo == 0 {
+      return None;
+    }
+
+    let cp0 = &keys[lo - 1];
+
+    match (cp0.interpolation, keys.get(lo)) {
+      (Interpolation::Step(threshold), Some(cp1)) if (t - cp0.t) / (cp1.t - cp0.t) >= threshold => {
+        Some(&cp1.value)
+      },
+      _ => Some(&cp0.value)
+    }
+  }
+
+  /// Turn a `SplineManifest` into a discrete spline.
+  pub fn from_manifest(manifest: SplineManifest<T::Deserialized>) -> Self where T: SplineDeserializerAdapter {
+    DiscreteSpline {
+      keys: Spline::from_manifest(manifest).into_keys()
+    }
+  }
+
+  /// Turn a discrete spline into a `SplineManifest`.
+  pub fn to_manifest(&self) -> SplineManifest<T::Serialized> where T: SplineSerializerAdapter {
+    SplineManifest::Keys(self.keys.iter().map(|key| {
+      Key::new(key.t, key.value.to_serialized(), key.interpolation)
+    }).collect())
+  }
+}
+
+/// Key of a discrete spline in the store.
+pub type DiscreteSplineKey<T> = SplineResourceKey<DiscreteSpline<T>>;
+
+impl<T> Load for DiscreteSpline<T> where T: 'static + SplineDeserializerAdapter {
+  fn load<P>(path: P, _: &mut Store) -> Result<LoadResult<Self>, LoadError> where P: AsRef<Path> {
+    let path = path.as_ref();
+
+    let file = File::open(path).map_err(|_| LoadError::FileNotFound(path.to_path_buf()))?;
+    let manifest: SplineManifest<T::Deserialized> = from_reader(file).map_err(|e| LoadError::ParseFailed(format!("{:?}", e)))?;
+
+    Ok(DiscreteSpline::from_manifest(manifest).into())
+  }
+}
+
+impl<T> Save for DiscreteSpline<T> where T: SplineSerializerAdapter {
+  fn save<P>(&self, path: P) -> Result<(), SaveError> where P: AsRef<Path> {
+    let path = path.as_ref();
+
+    let file = File::create(path).map_err(|_| SaveError::CannotCreate(path.to_path_buf()))?;
+    to_writer_pretty(file, &self.to_manifest()).map_err(|e| SaveError::SerializationFailed(format!("{:?}", e)))
+  }
+}
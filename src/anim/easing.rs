@@ -0,0 +1,229 @@
+//! Easing functions.
+//!
+//! Easing functions remap a normalized time (`[0;1]`) to shape transitions – slow starts, bouncy
+//! ends and so on. They’re mostly used through `Interpolation::Ease` in splines, but they can be
+//! used on their own as well.
+//!
+//! Every family comes in three flavours: *in* (the effect happens at the beginning), *out* (the
+//! effect happens at the end) and *in-out* (the effect happens at both ends).
+
+use std::f32::consts;
+
+use anim::spline::Time;
+
+/// Easing function.
+#[derive(Copy, Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum Easing {
+  /// Quadratic ease-in.
+  #[serde(rename = "quad_in")]
+  QuadIn,
+  /// Quadratic ease-out.
+  #[serde(rename = "quad_out")]
+  QuadOut,
+  /// Quadratic ease-in-out.
+  #[serde(rename = "quad_in_out")]
+  QuadInOut,
+  /// Cubic ease-in.
+  #[serde(rename = "cubic_in")]
+  CubicIn,
+  /// Cubic ease-out.
+  #[serde(rename = "cubic_out")]
+  CubicOut,
+  /// Cubic ease-in-out.
+  #[serde(rename = "cubic_in_out")]
+  CubicInOut,
+  /// Exponential ease-in.
+  #[serde(rename = "expo_in")]
+  ExpoIn,
+  /// Exponential ease-out.
+  #[serde(rename = "expo_out")]
+  ExpoOut,
+  /// Exponential ease-in-out.
+  #[serde(rename = "expo_in_out")]
+  ExpoInOut,
+  /// Ease-in going slightly backwards first.
+  #[serde(rename = "back_in")]
+  BackIn,
+  /// Ease-out overshooting slightly before settling.
+  #[serde(rename = "back_out")]
+  BackOut,
+  /// Combination of `BackIn` and `BackOut`.
+  #[serde(rename = "back_in_out")]
+  BackInOut,
+  /// Ease-in oscillating like a spring.
+  #[serde(rename = "elastic_in")]
+  ElasticIn,
+  /// Ease-out oscillating like a spring.
+  #[serde(rename = "elastic_out")]
+  ElasticOut,
+  /// Combination of `ElasticIn` and `ElasticOut`.
+  #[serde(rename = "elastic_in_out")]
+  ElasticInOut,
+  /// Ease-in bouncing like a ball.
+  #[serde(rename = "bounce_in")]
+  BounceIn,
+  /// Ease-out bouncing like a ball.
+  #[serde(rename = "bounce_out")]
+  BounceOut,
+  /// Combination of `BounceIn` and `BounceOut`.
+  #[serde(rename = "bounce_in_out")]
+  BounceInOut
+}
+
+impl Easing {
+  /// Ease a normalized time.
+  pub fn ease(self, t: Time) -> Time {
+    self.ease_with_derivatives(t).0
+  }
+
+  /// Ease a normalized time, also returning the first and second derivatives of the easing
+  /// function at this time.
+  pub fn ease_with_derivatives(self, t: Time) -> (Time, Time, Time) {
+    let (family, flavour) = self.split();
+
+    match flavour {
+      Flavour::In => family.ease_in(t),
+      Flavour::Out => {
+        let (f, df, ddf) = family.ease_in(1. - t);
+        (1. - f, df, -ddf)
+      },
+      Flavour::InOut => {
+        if t < 0.5 {
+          let (f, df, ddf) = family.ease_in(2. * t);
+          (f * 0.5, df, ddf * 2.)
+        } else {
+          let (f, df, ddf) = family.ease_in(2. - 2. * t);
+          (1. - f * 0.5, df, -ddf * 2.)
+        }
+      }
+    }
+  }
+
+  /// Family and flavour of the easing function.
+  pub fn split(self) -> (Family, Flavour) {
+    match self {
+      Easing::QuadIn => (Family::Quad, Flavour::In),
+      Easing::QuadOut => (Family::Quad, Flavour::Out),
+      Easing::QuadInOut => (Family::Quad, Flavour::InOut),
+      Easing::CubicIn => (Family::Cubic, Flavour::In),
+      Easing::CubicOut => (Family::Cubic, Flavour::Out),
+      Easing::CubicInOut => (Family::Cubic, Flavour::InOut),
+      Easing::ExpoIn => (Family::Expo, Flavour::In),
+      Easing::ExpoOut => (Family::Expo, Flavour::Out),
+      Easing::ExpoInOut => (Family::Expo, Flavour::InOut),
+      Easing::BackIn => (Family::Back, Flavour::In),
+      Easing::BackOut => (Family::Back, Flavour::Out),
+      Easing::BackInOut => (Family::Back, Flavour::InOut),
+      Easing::ElasticIn => (Family::Elastic, Flavour::In),
+      Easing::ElasticOut => (Family::Elastic, Flavour::Out),
+      Easing::ElasticInOut => (Family::Elastic, Flavour::InOut),
+      Easing::BounceIn => (Family::Bounce, Flavour::In),
+      Easing::BounceOut => (Family::Bounce, Flavour::Out),
+      Easing::BounceInOut => (Family::Bounce, Flavour::InOut)
+    }
+  }
+}
+
+/// Easing family. Every family is defined by its ease-in function; the other flavours are derived
+/// from it.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Family {
+  /// Quadratic easing.
+  Quad,
+  /// Cubic easing.
+  Cubic,
+  /// Exponential easing.
+  Expo,
+  /// Easing going slightly backwards or overshooting.
+  Back,
+  /// Easing oscillating like a spring.
+  Elastic,
+  /// Easing bouncing like a ball.
+  Bounce
+}
+
+/// Easing flavour, telling at which ends of a transition the effect of a family happens.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Flavour {
+  /// The effect happens at the beginning.
+  In,
+  /// The effect happens at the end; the ease-in function is mirrored.
+  Out,
+  /// The effect happens at both ends; the first half is the ease-in function and the second half
+  /// the ease-out one, both compressed in time.
+  InOut
+}
+
+// Overshoot of the back easing.
+pub(crate) const BACK_C1: f32 = 1.70158;
+pub(crate) const BACK_C3: f32 = BACK_C1 + 1.;
+
+// Period of the elastic easing.
+pub(crate) const ELASTIC_C4: f32 = 2. * consts::PI / 3.;
+
+// Curvature of the parabolas of the bouncing ease-out function.
+pub(crate) const BOUNCE_N1: f32 = 7.5625;
+
+// Parabolas of the bouncing ease-out function, from the last one to the first one, as (start,
+// center, offset): from its start on, a parabola is BOUNCE_N1 * (t - center)² + offset.
+pub(crate) const BOUNCE_PARABOLAS: [(f32, f32, f32); 4] = [
+  (2.5 / 2.75, 2.625 / 2.75, 0.984375),
+  (2. / 2.75, 2.25 / 2.75, 0.9375),
+  (1. / 2.75, 1.5 / 2.75, 0.75),
+  (0., 0., 0.)
+];
+
+impl Family {
+  // Ease-in function with its first and second derivatives.
+  fn ease_in(self, t: Time) -> (Time, Time, Time) {
+    match self {
+      Family::Quad => (t * t, 2. * t, 2.),
+      Family::Cubic => (t * t * t, 3. * t * t, 6. * t),
+      Family::Expo => {
+        if t <= 0. {
+          (0., 0., 0.)
+        } else {
+          let k = 10. * consts::LN_2;
+          let f = (10. * t - 10.).exp2();
+
+          (f, k * f, k * k * f)
+        }
+      },
+      Family::Back => {
+        (BACK_C3 * t * t * t - BACK_C1 * t * t, 3. * BACK_C3 * t * t - 2. * BACK_C1 * t, 6. * BACK_C3 * t - 2. * BACK_C1)
+      },
+      Family::Elastic => {
+        if t <= 0. {
+          (0., 0., 0.)
+        } else if t >= 1. {
+          (1., 0., 0.)
+        } else {
+          // f = -g * h, with g an exponential and h a sine
+          let k = 10. * consts::LN_2;
+          let w = 10. * ELASTIC_C4;
+          let phase = (10. * t - 10.75) * ELASTIC_C4;
+          let g = (10. * t - 10.).exp2();
+          let h = phase.sin();
+          let dh = w * phase.cos();
+          let ddh = -w * w * h;
+
+          (-g * h, -g * (k * h + dh), -g * (k * k * h + 2. * k * dh + ddh))
+        }
+      },
+      Family::Bounce => {
+        let (f, df, ddf) = bounce_out(1. - t);
+        (1. - f, df, -ddf)
+      }
+    }
+  }
+}
+
+// Bouncing ease-out function with its first and second derivatives; it’s made of four parabolas.
+fn bounce_out(t: Time) -> (Time, Time, Time) {
+  // the first parabola also extends before 0
+  let last = BOUNCE_PARABOLAS.len() - 1;
+  let &(_, c, offset) = BOUNCE_PARABOLAS.iter().find(|&&(start, _, _)| t >= start).unwrap_or(&BOUNCE_PARABOLAS[last]);
+  let x = t - c;
+
+  (BOUNCE_N1 * x * x + offset, 2. * BOUNCE_N1 * x, 2. * BOUNCE_N1)
+}
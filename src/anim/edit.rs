@@ -1,19 +1,46 @@
-use serde_json::from_reader;
-use std::collections::HashMap;
+use serde_json::{from_reader, to_writer_pretty};
+use std::cell::{Ref, RefCell};
+use std::cmp;
+use std::collections::{BTreeMap, HashMap};
+use std::f64;
+use std::fmt;
 use std::fs::File;
+use std::mem;
 use std::path::{Path, PathBuf};
 
-use sys::resource::{CacheKey, Load, LoadError, LoadResult, Store, StoreKey};
+use sys::resource::{CacheKey, Load, LoadError, LoadResult, Save, SaveError, Store, StoreKey};
 
 /// Time.
 pub type Time = f64;
 
+/// Context in which a `Clip` is played by a `Cut`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CutContext {
+  /// Time of the timeline.
+  pub global_time: Time,
+  /// Time in the clip, taking into account where the cut is instantiated and where it starts in
+  /// the clip: `global_time - inst_time + in_time`.
+  pub local_time: Time,
+  /// Normalized progress in the cut, from 0 at its beginning to 1 at its end.
+  pub progress: Time,
+  /// Duration of the cut.
+  pub dur: Time,
+  /// Index of the track the cut lives in.
+  pub track: usize
+}
+
 pub struct Clip<'a, A> where A: 'a {
-  gen_node: Box<Fn(Time) -> A + 'a>
+  gen_node: Box<Fn(CutContext) -> A + 'a>
 }
 
 impl<'a, A> Clip<'a, A> {
+  /// Create a clip from a function of its local time.
   pub fn new<F>(f: F) -> Self where F: 'a + Fn(Time) -> A {
+    Self::with_context(move |ctx: CutContext| f(ctx.local_time))
+  }
+
+  /// Create a clip from a function of the context it’s played in.
+  pub fn with_context<F>(f: F) -> Self where F: 'a + Fn(CutContext) -> A {
     Clip {
       gen_node: Box::new(f)
     }
@@ -31,6 +58,11 @@ pub struct Cut<'a, 'b, A> where A: 'a, 'a: 'b {
 }
 
 impl<'a, 'b, A> Cut<'a, 'b, A> where A :'a, 'a: 'b {
+  /// Create a new cut.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `in_time > out_time`.
   pub fn new(in_time: Time, out_time: Time, inst_time: Time, clip: &'b Clip<'a, A>) -> Self {
     assert!(in_time <= out_time);
 
@@ -46,6 +78,21 @@ impl<'a, 'b, A> Cut<'a, 'b, A> where A :'a, 'a: 'b {
   pub fn dur(&self) -> Time {
     self.out_time - self.in_time
   }
+
+  /// Context in which the clip is played at a given time of the timeline, given the index of the
+  /// track the cut lives in.
+  pub fn context(&self, t: Time, track: usize) -> CutContext {
+    let dur = self.dur();
+    let elapsed = t - self.inst_time;
+
+    CutContext {
+      global_time: t,
+      local_time: elapsed + self.in_time,
+      progress: if dur > 0. { elapsed / dur } else { 0. },
+      dur: dur,
+      track: track
+    }
+  }
 }
 
 /// A track gathers `Cut`s and its purpose is to be used inside a `Timeline`.
@@ -75,22 +122,38 @@ impl<'a, 'b, 'c, A> From<&'c [Cut<'a, 'b, A>]> for Track<'a, 'b, A> where A: Clo
 }
 
 /// A timeline gathers tracks used to build up the visual aspect of the demo.
+///
+/// Cuts and overlaps are indexed by time so that finding the ones active at a given time doesn’t
+/// require scanning all of them. The indices are built on the first query following the addition
+/// of tracks or overlaps, so that adding many of them in a row stays cheap.
 pub struct Timeline<'a, 'b, A> where A: 'a, 'a: 'b {
   tracks: Vec<Track<'a, 'b, A>>,
-  overlaps: Vec<Overlap<'a, A>>
+  overlaps: Vec<Overlap<'b, A>>,
+  // (track, cut) indices of the cuts; None until first queried after an addition
+  cut_index: RefCell<Option<IntervalIndex<(usize, usize)>>>,
+  // indices of the overlaps; None until first queried after an addition
+  overlap_index: RefCell<Option<IntervalIndex<usize>>>,
+  // (track, cut) indices of the active cuts, kept around so that playing doesn’t allocate
+  active: RefCell<Vec<(usize, usize)>>
 }
 
 impl<'a, 'b, A> Timeline<'a, 'b, A> where A: 'a, 'a: 'b {
   pub fn new() -> Self {
     Timeline {
       tracks: Vec::new(),
-      overlaps: Vec::new()
+      overlaps: Vec::new(),
+      cut_index: RefCell::new(None),
+      overlap_index: RefCell::new(None),
+      active: RefCell::new(Vec::new())
     }
   }
 
   /// Turn a TimelineManifest into a Timeline by providing a mapping between clips’ names and real
-  /// clips.
-  pub fn from_manifest(manifest: &TimelineManifest, mapping: &HashMap<String, &'b Clip<'a, A>>) -> Self {
+  /// clips, and a mapping between folds’ names and real folds.
+  ///
+  /// Cuts and overlaps that cannot be resolved are left out; use `TimelineManifest::validate` to
+  /// find out about them.
+  pub fn from_manifest(manifest: &TimelineManifest, mapping: &HashMap<String, &'b Clip<'a, A>>, folds: &HashMap<String, &'b Fold<'a, A>>) -> Self {
     let mut timeline = Self::new();
 
     for track_manifest in &manifest.tracks {
@@ -101,7 +164,9 @@ impl<'a, 'b, A> Timeline<'a, 'b, A> where A: 'a, 'a: 'b {
         let out_time = cut_manifest.out_time;
         let inst_time = cut_manifest.inst_time;
 
-        if let Some(clip) = mapping.get(&cut_manifest.clip).cloned() {
+        if in_time > out_time {
+          warn!("the cut of {:?} at {} ends before it starts", cut_manifest.clip, inst_time);
+        } else if let Some(clip) = mapping.get(&cut_manifest.clip).cloned() {
           track.add_cut(Cut::new(in_time, out_time, inst_time, clip));
         } else {
           warn!("the clip {:?} doesn’t exist", cut_manifest.clip);
@@ -111,45 +176,187 @@ impl<'a, 'b, A> Timeline<'a, 'b, A> where A: 'a, 'a: 'b {
       timeline.add_track(track);
     }
 
+    for overlap_manifest in &manifest.overlaps {
+      let inst_time = overlap_manifest.inst_time;
+      let dur = overlap_manifest.dur;
+
+      if let Some(fold) = folds.get(&overlap_manifest.fold).cloned() {
+        let overlap = Overlap::from_fold(inst_time, dur, &overlap_manifest.fold, fold, overlap_manifest.params.clone());
+        timeline.add_overlap(overlap);
+      } else {
+        warn!("the fold {:?} doesn’t exist", overlap_manifest.fold);
+      }
+    }
+
     timeline
   }
 
+  /// Turn a Timeline into a TimelineManifest by providing a mapping between clips’ names and real
+  /// clips. It’s the symmetric of `Timeline::from_manifest`.
+  ///
+  /// Clips are compared by address. Cuts which clip is not in the mapping are left out, as well
+  /// as overlaps not created from a named fold – see `Overlap::from_fold`.
+  pub fn to_manifest(&self, mapping: &HashMap<String, &'b Clip<'a, A>>) -> TimelineManifest {
+    let names: Vec<_> = mapping.iter().map(|(name, clip)| (*clip as *const Clip<'a, A>, name)).collect();
+
+    let tracks = self.tracks.iter().map(|track| {
+      let cuts = track.cuts.iter().filter_map(|cut| {
+        let name = names.iter().find(|&&(clip, _)| clip == cut.clip as *const _).map(|&(_, name)| name);
+
+        if name.is_none() {
+          warn!("a cut at {} plays an unnamed clip; skipping it", cut.inst_time);
+        }
+
+        name.map(|name| {
+          CutManifest {
+            in_time: cut.in_time,
+            out_time: cut.out_time,
+            inst_time: cut.inst_time,
+            clip: name.clone()
+          }
+        })
+      }).collect();
+
+      TrackManifest {
+        cuts: cuts
+      }
+    }).collect();
+
+    let overlaps = self.overlaps.iter().filter_map(|overlap| {
+      if overlap.named_fold.is_none() {
+        warn!("an overlap at {} has no named fold; skipping it", overlap.inst_time);
+      }
+
+      overlap.named_fold.as_ref().map(|&(ref name, ref params)| {
+        OverlapManifest {
+          inst_time: overlap.inst_time,
+          dur: overlap.dur,
+          fold: name.clone(),
+          params: params.clone()
+        }
+      })
+    }).collect();
+
+    TimelineManifest {
+      tracks: tracks,
+      overlaps: overlaps
+    }
+  }
+
   pub fn add_track(&mut self, track: Track<'a, 'b, A>) {
     self.tracks.push(track);
+    *self.cut_index.get_mut() = None;
   }
 
-  pub fn add_overlap(&mut self, overlap: Overlap<'a, A>) {
-    self.overlaps.push(overlap)
+  pub fn add_overlap(&mut self, overlap: Overlap<'b, A>) {
+    self.overlaps.push(overlap);
+    *self.overlap_index.get_mut() = None;
   }
 
+  /// Cuts active at a given time, along with the index of the track they live in. Cuts are sorted
+  /// by track, then in the order they were added to their track.
+  ///
+  /// The cuts are written to `cuts`, which is cleared first; reusing the same buffer from a query to
+  /// another avoids allocating.
+  pub fn active_cuts<'c>(&'c self, t: Time, cuts: &mut Vec<(usize, &'c Cut<'a, 'b, A>)>) {
+    let mut indices = self.active.borrow_mut();
+    self.stab_cuts(t, &mut indices);
+
+    cuts.clear();
+    cuts.extend(indices.iter().map(|&(i, j)| (i, &self.tracks[i].cuts[j])));
+  }
+
+  /// Play the timeline at a given time.
+  ///
+  /// Nothing is allocated unless several cuts are active, in which case their nodes are gathered
+  /// for the overlap to fold them.
   pub fn play(&self, t: Time) -> Played<A> {
-    let mut active_nodes = Vec::new();
+    // take the buffer rather than borrowing it, as clips might play the timeline themselves
+    let mut active = mem::replace(&mut *self.active.borrow_mut(), Vec::new());
+    self.stab_cuts(t, &mut active);
 
-    // populate the active nodes
-    for track in &self.tracks {
-      for cut in &track.cuts {
-        if cut.inst_time <= t && t <= cut.inst_time + cut.dur() {
-          active_nodes.push((cut.clip.gen_node)(t));
-        }
-      }
-    }
+    let node = |&(i, j): &(usize, usize)| {
+      let cut = &self.tracks[i].cuts[j];
+      (cut.clip.gen_node)(cut.context(t, i))
+    };
 
     // apply overlap if needed
-    match active_nodes.len() {
+    let played = match active.len() {
       0 => Played::Inactive,
-      1 => active_nodes.pop().map(Played::Resolved).unwrap_or(Played::Inactive),
+      1 => Played::Resolved(node(&active[0])),
       _ => {
         // we need to seek for an overlap here because we have strictly more than one node in hands
         self.find_overlap(t).map(|overlap| {
-          Played::Resolved((overlap.fold)(active_nodes))
+          Played::Resolved((overlap.fold)(active.iter().map(&node).collect()))
         }).unwrap_or(Played::NoOverlap)
       }
+    };
+
+    *self.active.borrow_mut() = active;
+    played
+  }
+
+  /// Check the timeline for cuts extending beyond the demo’s length – if provided – and for time
+  /// ranges with no active cut or with several active cuts and no overlap. Cuts touching at a
+  /// single instant are both active then, so they need an overlap there as well.
+  pub fn validate(&self, length: Option<Time>) -> Vec<TimelineIssue> {
+    let mut spans = Vec::new();
+
+    for (i, track) in self.tracks.iter().enumerate() {
+      for (j, cut) in track.cuts.iter().enumerate() {
+        spans.push(CutSpan::new(i, j, cut.inst_time, cut.dur()));
+      }
     }
+
+    let overlaps: Vec<_> = self.overlaps.iter().map(|o| (o.inst_time, o.inst_time + o.dur)).collect();
+    let mut issues = Vec::new();
+
+    check_spans(&spans, &overlaps, length, &mut issues);
+    issues
   }
 
   /// Find an active overlap at the given time.
   fn find_overlap(&self, t: Time) -> Option<&Overlap<A>> {
-    self.overlaps.iter().find(|x| x.inst_time <= t && t <= x.inst_time + x.dur)
+    // the first overlap added wins
+    let mut first: Option<usize> = None;
+    self.overlap_index().stab(t, &mut |i| first = Some(first.map_or(i, |f| cmp::min(f, i))));
+
+    first.map(|i| &self.overlaps[i])
+  }
+
+  // Write the (track, cut) indices of the cuts active at a given time to indices, sorted.
+  fn stab_cuts(&self, t: Time, indices: &mut Vec<(usize, usize)>) {
+    indices.clear();
+    self.cut_index().stab(t, &mut |ij| indices.push(ij));
+    indices.sort();
+  }
+
+  // Index of the cuts, built if tracks were added since the last query.
+  fn cut_index(&self) -> Ref<IntervalIndex<(usize, usize)>> {
+    if self.cut_index.borrow().is_none() {
+      let intervals = self.tracks.iter().enumerate().flat_map(|(i, track)| {
+        track.cuts.iter().enumerate().map(move |(j, cut)| {
+          (cut.inst_time, cut.inst_time + cut.dur(), (i, j))
+        })
+      }).collect();
+
+      *self.cut_index.borrow_mut() = Some(IntervalIndex::new(intervals));
+    }
+
+    Ref::map(self.cut_index.borrow(), |index| index.as_ref().unwrap())
+  }
+
+  // Index of the overlaps, built if overlaps were added since the last query.
+  fn overlap_index(&self) -> Ref<IntervalIndex<usize>> {
+    if self.overlap_index.borrow().is_none() {
+      let intervals = self.overlaps.iter().enumerate().map(|(i, overlap)| {
+        (overlap.inst_time, overlap.inst_time + overlap.dur, i)
+      }).collect();
+
+      *self.overlap_index.borrow_mut() = Some(IntervalIndex::new(intervals));
+    }
+
+    Ref::map(self.overlap_index.borrow(), |index| index.as_ref().unwrap())
   }
 }
 
@@ -163,9 +370,47 @@ pub enum Played<A> {
   Inactive
 }
 
-#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct TimelineManifest {
-  pub tracks: Vec<TrackManifest>
+  pub tracks: Vec<TrackManifest>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub overlaps: Vec<OverlapManifest>
+}
+
+impl TimelineManifest {
+  /// Check the manifest against the names of the clips and folds it will be resolved with and,
+  /// optionally, the length of the demo.
+  ///
+  /// Cuts and overlaps that cannot be resolved are reported and then ignored when checking the
+  /// time ranges of the timeline, as `Timeline::from_manifest` would leave them out.
+  pub fn validate<C, F>(&self, clips: &HashMap<String, C>, folds: &HashMap<String, F>, length: Option<Time>) -> Vec<TimelineIssue> {
+    let mut issues = Vec::new();
+    let mut spans = Vec::new();
+    let mut overlaps = Vec::new();
+
+    for (i, track) in self.tracks.iter().enumerate() {
+      for (j, cut) in track.cuts.iter().enumerate() {
+        if cut.in_time > cut.out_time {
+          issues.push(TimelineIssue::ReversedCut { track: i, cut: j });
+        } else if !clips.contains_key(&cut.clip) {
+          issues.push(TimelineIssue::UnknownClip { track: i, cut: j, clip: cut.clip.clone() });
+        } else {
+          spans.push(CutSpan::new(i, j, cut.inst_time, cut.out_time - cut.in_time));
+        }
+      }
+    }
+
+    for (i, overlap) in self.overlaps.iter().enumerate() {
+      if folds.contains_key(&overlap.fold) {
+        overlaps.push((overlap.inst_time, overlap.inst_time + overlap.dur));
+      } else {
+        issues.push(TimelineIssue::UnknownFold { overlap: i, fold: overlap.fold.clone() });
+      }
+    }
+
+    check_spans(&spans, &overlaps, length, &mut issues);
+    issues
+  }
 }
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
@@ -192,12 +437,21 @@ impl Load for TimelineManifest {
   }
 }
 
-#[derive(Debug, Deserialize, PartialEq, Serialize)]
+impl Save for TimelineManifest {
+  fn save<P>(&self, path: P) -> Result<(), SaveError> where P: AsRef<Path> {
+    let path = path.as_ref();
+
+    let file = File::create(path).map_err(|_| SaveError::CannotCreate(path.to_path_buf()))?;
+    to_writer_pretty(file, self).map_err(|e| SaveError::SerializationFailed(format!("{:?}", e)))
+  }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct TrackManifest {
   pub cuts: Vec<CutManifest>
 }
 
-#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct CutManifest {
   pub in_time: Time,
   pub out_time: Time,
@@ -205,12 +459,41 @@ pub struct CutManifest {
   pub clip: String
 }
 
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct OverlapManifest {
+  pub inst_time: Time,
+  pub dur: Time,
+  pub fold: String,
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub params: FoldParams
+}
+
+/// Named parameters of a `Fold`.
+pub type FoldParams = BTreeMap<String, f32>;
+
+/// A fold is a parameterized function consuming clips’ outputs down to a single one. Folds are
+/// referred to by name in timeline manifests, and the same fold can be used by several overlaps
+/// with different parameters.
+pub struct Fold<'a, A> where A: 'a {
+  fold: Box<Fn(Vec<A>, &FoldParams) -> A + 'a>
+}
+
+impl<'a, A> Fold<'a, A> {
+  pub fn new<F>(f: F) -> Self where F: 'a + Fn(Vec<A>, &FoldParams) -> A {
+    Fold {
+      fold: Box::new(f)
+    }
+  }
+}
+
 /// An overlap is a fold consuming clips’ outputs down to a single one. It’s used whenever two cuts
 /// overlap and need to be merged into a single one. It can be used for styling effect or transitions.
 pub struct Overlap<'a, A> {
   pub inst_time: Time,
   pub dur: Time,
   pub fold: Box<Fn(Vec<A>) -> A + 'a>,
+  /// Name and parameters of the fold the overlap was created from, if any.
+  pub named_fold: Option<(String, FoldParams)>
 }
 
 impl<'a, A> Overlap<'a, A> {
@@ -218,7 +501,221 @@ impl<'a, A> Overlap<'a, A> {
     Overlap {
       inst_time: inst_time,
       dur: dur,
-      fold: Box::new(f)
+      fold: Box::new(f),
+      named_fold: None
+    }
+  }
+
+  /// Create an overlap from a named fold and its parameters.
+  pub fn from_fold<'f>(inst_time: Time, dur: Time, name: &str, fold: &'a Fold<'f, A>, params: FoldParams) -> Self where 'f: 'a {
+    let fold_params = params.clone();
+
+    Overlap {
+      inst_time: inst_time,
+      dur: dur,
+      fold: Box::new(move |nodes: Vec<A>| (fold.fold)(nodes, &fold_params)),
+      named_fold: Some((name.to_owned(), params))
+    }
+  }
+}
+
+/// Issue found while validating a timeline.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TimelineIssue {
+  /// A cut plays a clip that doesn’t exist.
+  UnknownClip { track: usize, cut: usize, clip: String },
+  /// An overlap uses a fold that doesn’t exist.
+  UnknownFold { overlap: usize, fold: String },
+  /// A cut has its input time after its output time.
+  ReversedCut { track: usize, cut: usize },
+  /// A cut ends after the end of the demo.
+  BeyondEnd { track: usize, cut: usize, end: Time },
+  /// Several cuts are active at once but there’s no overlap to fold them. `start` and `end` are
+  /// equal when cuts only meet at an instant.
+  UncoveredCuts { start: Time, end: Time },
+  /// No cut is active.
+  Gap { start: Time, end: Time }
+}
+
+impl fmt::Display for TimelineIssue {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      TimelineIssue::UnknownClip { track, cut, ref clip } => write!(f, "cut {} of track {} plays the unknown clip {:?}", cut, track, clip),
+      TimelineIssue::UnknownFold { overlap, ref fold } => write!(f, "overlap {} uses the unknown fold {:?}", overlap, fold),
+      TimelineIssue::ReversedCut { track, cut } => write!(f, "cut {} of track {} ends before it starts", cut, track),
+      TimelineIssue::BeyondEnd { track, cut, end } => write!(f, "cut {} of track {} ends after the demo, at {}", cut, track, end),
+      TimelineIssue::UncoveredCuts { start, end } => write!(f, "several cuts are active with no overlap from {} to {}", start, end),
+      TimelineIssue::Gap { start, end } => write!(f, "no cut is active from {} to {}", start, end)
+    }
+  }
+}
+
+// Time span of a cut in a timeline.
+struct CutSpan {
+  track: usize,
+  cut: usize,
+  start: Time,
+  end: Time
+}
+
+impl CutSpan {
+  fn new(track: usize, cut: usize, inst_time: Time, dur: Time) -> Self {
+    CutSpan {
+      track: track,
+      cut: cut,
+      start: inst_time,
+      end: inst_time + dur
+    }
+  }
+}
+
+// Report cuts extending beyond the demo’s length, uncovered cuts and gaps.
+//
+// The timeline is split at every cut and overlap boundary and each piece – the boundaries themselves
+// and the ranges between them – is checked on its own; contiguous pieces with the same issue are then
+// merged. Gaps are looked for from 0 to the length of the demo, or to the end of the last cut. Cuts
+// are active at both their ends, so cuts meeting at a single instant – even in the same track – are
+// reported as uncovered at that instant unless an overlap covers it.
+fn check_spans(spans: &[CutSpan], overlaps: &[(Time, Time)], length: Option<Time>, issues: &mut Vec<TimelineIssue>) {
+  if let Some(length) = length {
+    for span in spans.iter().filter(|span| span.end > length) {
+      issues.push(TimelineIssue::BeyondEnd { track: span.track, cut: span.cut, end: span.end });
+    }
+  }
+
+  let horizon = length.unwrap_or_else(|| spans.iter().fold(0., |end, span| span.end.max(end)));
+
+  let mut bounds = vec![0., horizon];
+  bounds.extend(spans.iter().flat_map(|span| vec![span.start, span.end]));
+  bounds.extend(overlaps.iter().flat_map(|&(start, end)| vec![start, end]));
+  bounds.retain(|&t| t >= 0. && t <= horizon);
+  bounds.sort_by(|a, b| a.partial_cmp(b).unwrap());
+  bounds.dedup();
+
+  let mut pieces = Vec::with_capacity(2 * bounds.len());
+
+  for (k, &start) in bounds.iter().enumerate() {
+    pieces.push((start, start));
+
+    if let Some(&end) = bounds.get(k + 1) {
+      pieces.push((start, end));
     }
   }
+
+  let mut ranges: Vec<TimelineIssue> = Vec::new();
+
+  for (start, end) in pieces {
+    let mid = (start + end) * 0.5;
+    let active = spans.iter().filter(|span| span.start <= mid && mid <= span.end).count();
+    let covered = overlaps.iter().any(|&(a, b)| a <= mid && mid <= b);
+
+    let issue = if active == 0 {
+      // gaps are reported over ranges only
+      if start == end {
+        continue;
+      }
+
+      TimelineIssue::Gap { start: start, end: end }
+    } else if active > 1 && !covered {
+      TimelineIssue::UncoveredCuts { start: start, end: end }
+    } else {
+      continue;
+    };
+
+    // merge with the previous range if it’s the same issue and they’re contiguous
+    let merged = match (ranges.last_mut(), &issue) {
+      (Some(&mut TimelineIssue::Gap { end: ref mut prev_end, .. }), &TimelineIssue::Gap { start, end }) |
+      (Some(&mut TimelineIssue::UncoveredCuts { end: ref mut prev_end, .. }), &TimelineIssue::UncoveredCuts { start, end }) => {
+        if *prev_end == start {
+          *prev_end = end;
+          true
+        } else {
+          false
+        }
+      },
+      _ => false
+    };
+
+    if !merged {
+      ranges.push(issue);
+    }
+  }
+
+  issues.extend(ranges);
+}
+
+// Index of closed time intervals, finding the ones containing a given time without scanning all of
+// them.
+//
+// Intervals are sorted by start and a complete binary tree stores the latest end of the intervals
+// below each of its nodes, so that intervals starting after the queried time and subtrees ending
+// before it are skipped. A visited subtree which intervals all start at or before the queried time
+// ends at or after it, so it always contains an interval to report; only the subtrees straddling
+// the last interval starting at or before that time – one per level – can be visited for nothing.
+// A query reporting k intervals then visits O((k + 1) · log n) nodes.
+struct IntervalIndex<V> {
+  // intervals sorted by start
+  intervals: Vec<(Time, Time, V)>,
+  // latest ends of the nodes of the tree, the root being at 1 and leaves starting at leaves
+  max_ends: Vec<Time>,
+  leaves: usize
+}
+
+impl<V> IntervalIndex<V> where V: Copy {
+  fn new(mut intervals: Vec<(Time, Time, V)>) -> Self {
+    intervals.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+    let leaves = intervals.len().next_power_of_two();
+    let mut max_ends = vec![f64::NEG_INFINITY; 2 * leaves];
+
+    for (i, interval) in intervals.iter().enumerate() {
+      max_ends[leaves + i] = interval.1;
+    }
+
+    for node in (1..leaves).rev() {
+      max_ends[node] = max_ends[2 * node].max(max_ends[2 * node + 1]);
+    }
+
+    IntervalIndex {
+      intervals: intervals,
+      max_ends: max_ends,
+      leaves: leaves
+    }
+  }
+
+  // Call f with the values of the intervals containing t, sorted by start.
+  fn stab<F>(&self, t: Time, f: &mut F) where F: FnMut(V) {
+    // number of intervals starting at or before t
+    let mut lo = 0;
+    let mut hi = self.intervals.len();
+
+    while lo < hi {
+      let mid = (lo + hi) / 2;
+
+      if self.intervals[mid].0 <= t {
+        lo = mid + 1;
+      } else {
+        hi = mid;
+      }
+    }
+
+    self.visit(1, 0, self.leaves, lo, t, f);
+  }
+
+  // Call f with the intervals below node, covering the leaves [lo, hi), that start among the first
+  // count intervals and end at or after t.
+  fn visit<F>(&self, node: usize, lo: usize, hi: usize, count: usize, t: Time, f: &mut F) where F: FnMut(V) {
+    if lo >= count || self.max_ends[node] < t {
+      return;
+    }
+
+    if hi - lo == 1 {
+      f(self.intervals[lo].2);
+      return;
+    }
+
+    let mid = (lo + hi) / 2;
+    self.visit(2 * node, lo, mid, count, t, f);
+    self.visit(2 * node + 1, mid, hi, count, t, f);
+  }
 }
@@ -4,7 +4,28 @@
 //! specific, artistic and awesome code.
 //!
 //! While the `edit` module is for general execution and scheduling, the `spline` module is more
-//! about parameterization of a specific value you use with your objects.
+//! about parameterization of a specific value you use with your objects. The `arc_length` module
+//! provides constant-speed traversal of splines used as paths, the `easing` module gathers the
+//! easing functions splines can use, the `simplify` module reduces the number of keys of splines,
+//! the `bake` module samples splines into lookup tables, the `blend` module mixes several splines
+//! together and the `query` module finds crossings and extrema of scalar splines.
+//!
+//! Splines can be gathered in *animation clips* – see the `clip` module – so that all the animated
+//! parameters of a shot live in a single resource. Values that cannot be interpolated can be
+//! keyframed with the `discrete` module, objects can be moved around with the `transform`
+//! module and procedural noise can be layered on top of splines with the `noise` module. Splines
+//! can also be evaluated on the GPU by generating GLSL code with the `codegen` module.
 
+pub mod arc_length;
+pub mod bake;
+pub mod blend;
+pub mod clip;
+pub mod codegen;
+pub mod discrete;
+pub mod easing;
 pub mod edit;
+pub mod noise;
+pub mod query;
+pub mod simplify;
 pub mod spline;
+pub mod transform;
@@ -0,0 +1,478 @@
+//! Procedural noise.
+//!
+//! This module provides deterministic noise generators – value, Perlin and simplex noise in one,
+//! two or three dimensions – and a `NoiseModifier`, which layers noise on top of a spline. The
+//! latter is typically used for camera shakes, light flickers and other organic wobbles:
+//!
+//! ```json
+//! {
+//!   "spline": [ … ],
+//!   "noise": { "kind": "simplex", "seed": 42, "frequency": 4, "octaves": 3 },
+//!   "amplitude": [ … ]
+//! }
+//! ```
+//!
+//! The `spline` and `amplitude` fields follow the same format as spline files.
+
+use cgmath::InnerSpace;
+use serde_json::{from_reader, to_writer_pretty};
+use std::cmp::max;
+use std::fs::File;
+use std::path::Path;
+use std::u32;
+
+use anim::spline::{Interpolate, Spline, SplineDeserializerAdapter, SplineManifest, SplineResourceKey,
+                   SplineSerializerAdapter, Time};
+use linear::{Quat, Scale, V2, V3, V4};
+use sys::resource::{Load, LoadError, LoadResult, Save, SaveError, Store};
+
+/// Kind of noise.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum NoiseKind {
+  /// Random values on a grid, smoothly interpolated.
+  #[serde(rename = "value")]
+  Value,
+  /// Random gradients on a grid, smoothly interpolated.
+  #[serde(rename = "perlin")]
+  Perlin,
+  /// Random gradients on a simplex grid; cheaper than Perlin noise in higher dimensions and
+  /// without its axis-aligned artifacts.
+  #[serde(rename = "simplex")]
+  Simplex
+}
+
+/// Fractal noise generator.
+///
+/// Several *octaves* of noise are summed, each of them having twice the frequency and half the
+/// amplitude of the previous one. The result lies roughly in [-1, 1] and only depends on the
+/// parameters of the generator, so that a given seed always yields the same noise.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Noise {
+  pub kind: NoiseKind,
+  #[serde(default)]
+  pub seed: u32,
+  #[serde(default = "default_frequency")]
+  pub frequency: f32,
+  #[serde(default = "default_octaves")]
+  pub octaves: u32
+}
+
+fn default_frequency() -> f32 { 1. }
+
+fn default_octaves() -> u32 { 1 }
+
+impl Noise {
+  pub fn new(kind: NoiseKind, seed: u32, frequency: f32, octaves: u32) -> Self {
+    Noise {
+      kind: kind,
+      seed: seed,
+      frequency: frequency,
+      octaves: octaves
+    }
+  }
+
+  /// Sample one-dimensional noise.
+  pub fn sample1(&self, x: f32) -> f32 {
+    self.fractal([x, 0., 0.], 1)
+  }
+
+  /// Sample two-dimensional noise.
+  pub fn sample2(&self, p: V2<f32>) -> f32 {
+    self.fractal([p.x, p.y, 0.], 2)
+  }
+
+  /// Sample three-dimensional noise.
+  pub fn sample3(&self, p: V3<f32>) -> f32 {
+    self.fractal([p.x, p.y, p.z], 3)
+  }
+
+  // Sum the octaves of the noise, each of them using its own seed.
+  fn fractal(&self, p: [f32; 3], dims: usize) -> f32 {
+    let mut frequency = self.frequency;
+    let mut amplitude = 1.;
+    let mut sum = 0.;
+    let mut norm = 0.;
+
+    for octave in 0..max(self.octaves, 1) {
+      let seed = self.seed.wrapping_add(octave.wrapping_mul(0x9e3779b9));
+      let q = [p[0] * frequency, p[1] * frequency, p[2] * frequency];
+
+      let n = match self.kind {
+        NoiseKind::Value | NoiseKind::Perlin => grid_noise(self.kind, seed, q, dims),
+        NoiseKind::Simplex => match dims {
+          1 => simplex1(seed, q[0]),
+          2 => simplex2(seed, q[0], q[1]),
+          _ => simplex3(seed, q[0], q[1], q[2])
+        }
+      };
+
+      sum += amplitude * n;
+      norm += amplitude;
+      frequency *= 2.;
+      amplitude *= 0.5;
+    }
+
+    sum / norm
+  }
+}
+
+// Gradients pointing to the edges of a cube.
+const GRAD3: [[f32; 3]; 12] = [
+  [1., 1., 0.], [-1., 1., 0.], [1., -1., 0.], [-1., -1., 0.],
+  [1., 0., 1.], [-1., 0., 1.], [1., 0., -1.], [-1., 0., -1.],
+  [0., 1., 1.], [0., -1., 1.], [0., 1., -1.], [0., -1., -1.]
+];
+
+// Hash a lattice point.
+fn hash(seed: u32, p: [i32; 3]) -> u32 {
+  let mut h = seed.wrapping_mul(0x27d4eb2f)
+    ^ (p[0] as u32).wrapping_mul(0x85ebca77)
+    ^ (p[1] as u32).wrapping_mul(0xc2b2ae3d)
+    ^ (p[2] as u32).wrapping_mul(0x165667b1);
+
+  h ^= h >> 15;
+  h = h.wrapping_mul(0x2c1b3c6d);
+  h ^= h >> 12;
+  h = h.wrapping_mul(0x297a2d39);
+  h ^ (h >> 15)
+}
+
+// Map a hash to [-1, 1].
+fn hash_to_unit(h: u32) -> f32 {
+  (h as f64 / u32::MAX as f64 * 2. - 1.) as f32
+}
+
+// Quintic fade curve, which first and second derivatives vanish at 0 and 1.
+fn fade(t: f32) -> f32 {
+  t * t * t * (t * (t * 6. - 15.) + 10.)
+}
+
+// Value or Perlin noise, blending the 2^dims corners of the cell p lies in.
+fn grid_noise(kind: NoiseKind, seed: u32, p: [f32; 3], dims: usize) -> f32 {
+  let mut cell = [0; 3];
+  let mut frac = [0.; 3];
+  let mut weights = [0.; 3];
+
+  for d in 0..dims {
+    let fl = p[d].floor();
+    cell[d] = fl as i32;
+    frac[d] = p[d] - fl;
+    weights[d] = fade(frac[d]);
+  }
+
+  let mut r = 0.;
+
+  for corner in 0..(1i32 << dims) {
+    let mut lattice = cell;
+    let mut offset = [0.; 3];
+    let mut weight = 1.;
+
+    for d in 0..dims {
+      let bit = (corner >> d) & 1;
+
+      lattice[d] += bit;
+      offset[d] = frac[d] - bit as f32;
+      weight *= if bit == 1 { weights[d] } else { 1. - weights[d] };
+    }
+
+    let h = hash(seed, lattice);
+    let v = match kind {
+      NoiseKind::Value => hash_to_unit(h),
+      _ => gradient_dot(h, offset, dims)
+    };
+
+    r += weight * v;
+  }
+
+  r
+}
+
+// Dot product between a pseudo-random gradient and an offset, scaled to roughly fit [-1, 1].
+fn gradient_dot(h: u32, offset: [f32; 3], dims: usize) -> f32 {
+  match dims {
+    1 => 2. * hash_to_unit(h) * offset[0],
+    2 => {
+      let g = GRAD3[(h % 12) as usize];
+      1.4 * (g[0] * offset[0] + g[1] * offset[1])
+    },
+    _ => {
+      let g = GRAD3[(h % 12) as usize];
+      g[0] * offset[0] + g[1] * offset[1] + g[2] * offset[2]
+    }
+  }
+}
+
+fn simplex1(seed: u32, x: f32) -> f32 {
+  let i0 = x.floor();
+  let x0 = x - i0;
+  let x1 = x0 - 1.;
+
+  // gradients are integers in [-8, 8]
+  let grad = |i: f32, x: f32| {
+    let h = hash(seed, [i as i32, 0, 0]);
+    let g = 1. + (h & 7) as f32;
+
+    if h & 8 != 0 { -g * x } else { g * x }
+  };
+
+  let t0 = (1. - x0 * x0).powi(4);
+  let t1 = (1. - x1 * x1).powi(4);
+
+  0.395 * (t0 * grad(i0, x0) + t1 * grad(i0 + 1., x1))
+}
+
+fn simplex2(seed: u32, x: f32, y: f32) -> f32 {
+  let f2 = 0.5 * (3f32.sqrt() - 1.);
+  let g2 = (3. - 3f32.sqrt()) / 6.;
+
+  // skew the input space to find the simplex cell
+  let s = (x + y) * f2;
+  let i = (x + s).floor();
+  let j = (y + s).floor();
+  let t = (i + j) * g2;
+  let x0 = x - (i - t);
+  let y0 = y - (j - t);
+
+  // lower or upper triangle
+  let (i1, j1) = if x0 > y0 { (1., 0.) } else { (0., 1.) };
+
+  let corners = [
+    (0., 0., x0, y0),
+    (i1, j1, x0 - i1 + g2, y0 - j1 + g2),
+    (1., 1., x0 - 1. + 2. * g2, y0 - 1. + 2. * g2)
+  ];
+
+  let mut n = 0.;
+
+  for &(di, dj, cx, cy) in &corners {
+    let t = 0.5 - cx * cx - cy * cy;
+
+    if t > 0. {
+      let g = GRAD3[(hash(seed, [(i + di) as i32, (j + dj) as i32, 0]) % 12) as usize];
+      n += t.powi(4) * (g[0] * cx + g[1] * cy);
+    }
+  }
+
+  70. * n
+}
+
+fn simplex3(seed: u32, x: f32, y: f32, z: f32) -> f32 {
+  let f3 = 1. / 3.;
+  let g3 = 1. / 6.;
+
+  // skew the input space to find the simplex cell
+  let s = (x + y + z) * f3;
+  let i = (x + s).floor();
+  let j = (y + s).floor();
+  let k = (z + s).floor();
+  let t = (i + j + k) * g3;
+  let x0 = x - (i - t);
+  let y0 = y - (j - t);
+  let z0 = z - (k - t);
+
+  // find which of the six tetrahedra we’re in
+  let (i1, j1, k1, i2, j2, k2) = if x0 >= y0 {
+    if y0 >= z0 {
+      (1., 0., 0., 1., 1., 0.)
+    } else if x0 >= z0 {
+      (1., 0., 0., 1., 0., 1.)
+    } else {
+      (0., 0., 1., 1., 0., 1.)
+    }
+  } else if y0 < z0 {
+    (0., 0., 1., 0., 1., 1.)
+  } else if x0 < z0 {
+    (0., 1., 0., 0., 1., 1.)
+  } else {
+    (0., 1., 0., 1., 1., 0.)
+  };
+
+  let corners = [
+    (0., 0., 0., x0, y0, z0),
+    (i1, j1, k1, x0 - i1 + g3, y0 - j1 + g3, z0 - k1 + g3),
+    (i2, j2, k2, x0 - i2 + 2. * g3, y0 - j2 + 2. * g3, z0 - k2 + 2. * g3),
+    (1., 1., 1., x0 - 1. + 3. * g3, y0 - 1. + 3. * g3, z0 - 1. + 3. * g3)
+  ];
+
+  let mut n = 0.;
+
+  for &(di, dj, dk, cx, cy, cz) in &corners {
+    let t = 0.6 - cx * cx - cy * cy - cz * cz;
+
+    if t > 0. {
+      let lattice = [(i + di) as i32, (j + dj) as i32, (k + dk) as i32];
+      let g = GRAD3[(hash(seed, lattice) % 12) as usize];
+      n += t.powi(4) * (g[0] * cx + g[1] * cy + g[2] * cz);
+    }
+  }
+
+  32. * n
+}
+
+/// Types which values can be displaced by noise.
+pub trait Perturb: Sized {
+  /// Number of independent noise signals required to displace a value.
+  fn channels() -> usize;
+  /// Displace a value. `offsets` is exactly `Self::channels()` long.
+  fn perturb(self, offsets: &[f32]) -> Self;
+}
+
+impl Perturb for f32 {
+  fn channels() -> usize { 1 }
+
+  fn perturb(self, offsets: &[f32]) -> Self {
+    self + offsets[0]
+  }
+}
+
+impl Perturb for V2<f32> {
+  fn channels() -> usize { 2 }
+
+  fn perturb(self, offsets: &[f32]) -> Self {
+    self + V2::new(offsets[0], offsets[1])
+  }
+}
+
+impl Perturb for V3<f32> {
+  fn channels() -> usize { 3 }
+
+  fn perturb(self, offsets: &[f32]) -> Self {
+    self + V3::new(offsets[0], offsets[1], offsets[2])
+  }
+}
+
+impl Perturb for V4<f32> {
+  fn channels() -> usize { 4 }
+
+  fn perturb(self, offsets: &[f32]) -> Self {
+    self + V4::new(offsets[0], offsets[1], offsets[2], offsets[3])
+  }
+}
+
+/// The offsets form a rotation vector: its direction is the axis and its length the angle, in
+/// radians.
+impl Perturb for Quat<f32> {
+  fn channels() -> usize { 3 }
+
+  fn perturb(self, offsets: &[f32]) -> Self {
+    let v = V3::new(offsets[0], offsets[1], offsets[2]);
+    let angle = v.magnitude();
+
+    if angle == 0. {
+      return self;
+    }
+
+    let half = angle * 0.5;
+    self * Quat::from_sv(half.cos(), v * (half.sin() / angle))
+  }
+}
+
+impl Perturb for Scale {
+  fn channels() -> usize { 3 }
+
+  fn perturb(self, offsets: &[f32]) -> Self {
+    Scale::new(self.x + offsets[0], self.y + offsets[1], self.z + offsets[2])
+  }
+}
+
+/// Spline with noise layered on top of it.
+///
+/// The noise is sampled at the time the spline is sampled at and scaled by an amplitude spline,
+/// so that a shake can fade in and out. Every channel of the value – see `Perturb` – gets its
+/// own noise signal.
+#[derive(Clone, Debug)]
+pub struct NoiseModifier<T> {
+  pub spline: Spline<T>,
+  pub noise: Noise,
+  pub amplitude: Spline<f32>
+}
+
+impl<T> NoiseModifier<T> {
+  pub fn new(spline: Spline<T>, noise: Noise, amplitude: Spline<f32>) -> Self {
+    NoiseModifier {
+      spline: spline,
+      noise: noise,
+      amplitude: amplitude
+    }
+  }
+
+  /// Sample the amplitude of the noise at a given time.
+  ///
+  /// The amplitude spline is sampled with `Spline::try_clamped_sample`: it uses its extrapolation
+  /// modes if any and holds its first or last value otherwise. An empty amplitude spline disables
+  /// the noise.
+  pub fn sample_amplitude(&self, t: Time) -> f32 {
+    self.amplitude.try_clamped_sample(t).unwrap_or(0.)
+  }
+
+  /// Sample the noisy spline at a given time.
+  ///
+  /// # Return
+  ///
+  /// `None` if the underlying spline cannot be sampled at `t`.
+  pub fn sample(&self, t: Time) -> Option<T> where T: Interpolate + Perturb {
+    let value = match self.spline.sample(t) {
+      Some(value) => value,
+      None => return None
+    };
+
+    let amplitude = self.sample_amplitude(t);
+
+    if amplitude == 0. {
+      return Some(value);
+    }
+
+    let offsets: Vec<_> = (0..T::channels()).map(|c| {
+      // hash the channel in so that modifiers with adjacent seeds don’t share channels
+      let noise = Noise {
+        seed: hash(self.noise.seed, [c as i32, 0, 0]),
+        ..self.noise
+      };
+
+      amplitude * noise.sample1(t)
+    }).collect();
+
+    Some(value.perturb(&offsets))
+  }
+}
+
+#[derive(Deserialize, Serialize)]
+struct NoiseModifierManifest<T> {
+  spline: SplineManifest<T>,
+  noise: Noise,
+  amplitude: SplineManifest<f32>
+}
+
+/// Key of a noise modifier in the store.
+pub type NoiseModifierKey<T> = SplineResourceKey<NoiseModifier<T>>;
+
+impl<T> Load for NoiseModifier<T> where T: 'static + SplineDeserializerAdapter {
+  fn load<P>(path: P, _: &mut Store) -> Result<LoadResult<Self>, LoadError> where P: AsRef<Path> {
+    let path = path.as_ref();
+
+    let file = File::open(path).map_err(|_| LoadError::FileNotFound(path.to_path_buf()))?;
+    let manifest: NoiseModifierManifest<T::Deserialized> = from_reader(file).map_err(|e| LoadError::ParseFailed(format!("{:?}", e)))?;
+
+    let modifier = NoiseModifier {
+      spline: Spline::from_manifest(manifest.spline),
+      noise: manifest.noise,
+      amplitude: Spline::from_manifest(manifest.amplitude)
+    };
+
+    Ok(modifier.into())
+  }
+}
+
+impl<T> Save for NoiseModifier<T> where T: SplineSerializerAdapter {
+  fn save<P>(&self, path: P) -> Result<(), SaveError> where P: AsRef<Path> {
+    let path = path.as_ref();
+    let manifest = NoiseModifierManifest {
+      spline: self.spline.to_manifest(),
+      noise: self.noise,
+      amplitude: self.amplitude.to_manifest()
+    };
+
+    let file = File::create(path).map_err(|_| SaveError::CannotCreate(path.to_path_buf()))?;
+    to_writer_pretty(file, &manifest).map_err(|e| SaveError::SerializationFailed(format!("{:?}", e)))
+  }
+}
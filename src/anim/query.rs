@@ -0,0 +1,289 @@
+//! Queries on scalar splines.
+//!
+//! This module adds to `Spline<f32>` methods finding where a spline crosses a given value, where
+//! it reaches its local extrema and which values it spans over an interval. They’re typically used
+//! to derive events from curves – e.g. trigger a flash when an intensity crosses 1 – or to bound
+//! animated objects.
+//!
+//! Queries work with any interpolation and extrapolation mode: the spline is scanned with
+//! `SUBDIVISIONS` samples per segment, then candidates are refined numerically. Features narrower
+//! than a subdivision might then be missed, as well as features of repeating extrapolations more
+//! than `MAX_PERIODS` periods away from the keys.
+
+use anim::spline::{Extrapolation, Spline, Time};
+
+/// Number of samples a segment of a spline is scanned with.
+pub const SUBDIVISIONS: usize = 32;
+
+/// Maximum number of periods of a repeating extrapolation scanned on each side of the keys. Beyond
+/// them, the rest of the interval is scanned as a single stretch.
+pub const MAX_PERIODS: usize = 1024;
+
+// Number of iterations used to refine a crossing or an extremum.
+const REFINE_ITERATIONS: usize = 40;
+
+/// Direction in which a spline crosses a value.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CrossingDirection {
+  /// The spline goes above the value.
+  Rising,
+  /// The spline goes back below the value.
+  Falling
+}
+
+/// Time at which a spline crosses a value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Crossing {
+  pub t: Time,
+  pub direction: CrossingDirection
+}
+
+/// Kind of local extremum.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExtremumKind {
+  Minimum,
+  Maximum
+}
+
+/// Local extremum of a spline.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Extremum {
+  pub t: Time,
+  pub value: f32,
+  pub kind: ExtremumKind
+}
+
+impl Spline<f32> {
+  /// Find all the times at which the spline crosses `x` in [t0, t1], in increasing order.
+  ///
+  /// The spline is considered above `x` when its value is strictly greater than `x`. The time of a
+  /// crossing is then the first time at which the spline is on its new side – at a discontinuity
+  /// (e.g. with `Interpolation::Step`), it’s the time of the jump.
+  pub fn crossings(&self, x: f32, t0: Time, t1: Time) -> Vec<Crossing> {
+    let mut crossings = Vec::new();
+    let mut prev: Option<(Time, bool)> = None;
+
+    for t in scan_times(self, t0, t1) {
+      let above = match self.sample_inclusive(t) {
+        Some(v) => v > x,
+        None => {
+          prev = None;
+          continue;
+        }
+      };
+
+      if let Some((prev_t, prev_above)) = prev {
+        if above != prev_above {
+          let direction = if above { CrossingDirection::Rising } else { CrossingDirection::Falling };
+
+          crossings.push(Crossing {
+            t: refine_crossing(self, x, prev_t, t, prev_above),
+            direction: direction
+          });
+        }
+      }
+
+      prev = Some((t, above));
+    }
+
+    crossings
+  }
+
+  /// Find the local extrema of the spline in ]t0, t1[, in increasing order of time.
+  ///
+  /// Flat parts of the spline – such as holds – are not reported.
+  pub fn extrema(&self, t0: Time, t1: Time) -> Vec<Extremum> {
+    let mut extrema = Vec::new();
+    let mut window: Vec<(Time, f32)> = Vec::with_capacity(3);
+
+    for t in scan_times(self, t0, t1) {
+      let v = match self.sample_inclusive(t) {
+        Some(v) => v,
+        None => {
+          window.clear();
+          continue;
+        }
+      };
+
+      if window.len() == 3 {
+        window.remove(0);
+      }
+
+      window.push((t, v));
+
+      if window.len() == 3 {
+        let (ta, va) = window[0];
+        let (_, vb) = window[1];
+        let (tc, vc) = window[2];
+
+        let kind = if vb > va && vb > vc {
+          ExtremumKind::Maximum
+        } else if vb < va && vb < vc {
+          ExtremumKind::Minimum
+        } else {
+          continue;
+        };
+
+        let (t, value) = refine_extremum(self, ta, tc, kind);
+
+        extrema.push(Extremum {
+          t: t,
+          value: value,
+          kind: kind
+        });
+      }
+    }
+
+    extrema
+  }
+
+  /// Compute the range of values the spline spans over [t0, t1], as `(min, max)`.
+  ///
+  /// # Return
+  ///
+  /// `None` if the spline cannot be sampled anywhere in [t0, t1].
+  pub fn value_range(&self, t0: Time, t1: Time) -> Option<(f32, f32)> {
+    let scanned = scan_times(self, t0, t1).into_iter().filter_map(|t| self.sample_inclusive(t));
+    let extrema = self.extrema(t0, t1).into_iter().map(|e| e.value);
+
+    scanned.chain(extrema).fold(None, |range, v| {
+      match range {
+        Some((min, max)) => Some((f32::min(min, v), f32::max(max, v))),
+        None => Some((v, v))
+      }
+    })
+  }
+}
+
+// Times at which a spline is scanned over [t0, t1], in increasing order.
+//
+// The interval is clamped to the keys’ range on the sides the spline has no extrapolation for. It’s
+// split at every key and, with repeating extrapolation modes, at every period of the spline outside
+// of the keys’ range – up to MAX_PERIODS periods on each side – so that extrapolated parts are
+// scanned as finely as the spline itself.
+fn scan_times(spline: &Spline<f32>, t0: Time, t1: Time) -> Vec<Time> {
+  let keys = spline.keys();
+  let len = keys.len();
+
+  if len < 2 {
+    return Vec::new();
+  }
+
+  let first = keys[0].t;
+  let last = keys[len - 1].t;
+  let period = last - first;
+  let pre = spline.pre_extrapolation();
+  let post = spline.post_extrapolation();
+
+  // nothing can be sampled where there’s no extrapolation
+  let t0 = if pre.is_none() { t0.max(first) } else { t0 };
+  let t1 = if post.is_none() { t1.min(last) } else { t1 };
+
+  if t1 <= t0 {
+    return Vec::new();
+  }
+
+  let mut breakpoints = vec![t0, t1];
+  breakpoints.extend(keys.iter().map(|key| key.t).filter(|&t| t > t0 && t < t1));
+
+  if period > 0. {
+    // periods before the first key
+    if is_repeating(pre) && t0 < first {
+      for k in 1..periods(first - t0, period) {
+        breakpoints.push(first - period * k as Time);
+      }
+    }
+
+    // periods after the last key
+    if is_repeating(post) && t1 > last {
+      for k in 1..periods(t1 - last, period) {
+        breakpoints.push(last + period * k as Time);
+      }
+    }
+  }
+
+  breakpoints.retain(|&b| b >= t0 && b <= t1);
+  breakpoints.sort_by(|a, b| a.partial_cmp(b).unwrap());
+  breakpoints.dedup();
+
+  let mut times = Vec::new();
+
+  for w in breakpoints.windows(2) {
+    let (a, b) = (w[0], w[1]);
+    let subdivisions = if a >= first && b <= last { SUBDIVISIONS } else { SUBDIVISIONS * (len - 1) };
+
+    for i in 0..subdivisions {
+      times.push(a + (b - a) * i as Time / subdivisions as Time);
+    }
+  }
+
+  times.push(t1);
+  times
+}
+
+// Whether an extrapolation mode repeats the spline.
+fn is_repeating(extrapolation: Option<Extrapolation>) -> bool {
+  match extrapolation {
+    Some(Extrapolation::Repeat) | Some(Extrapolation::PingPong) | Some(Extrapolation::OffsetRepeat) => true,
+    _ => false
+  }
+}
+
+// Number of periods needed to cover a duration, capped to MAX_PERIODS.
+fn periods(duration: Time, period: Time) -> usize {
+  (duration / period).ceil().min(MAX_PERIODS as Time) as usize
+}
+
+// Find by bisection the first time in ]a, b] at which the spline has left the side of x it is on
+// at a.
+fn refine_crossing(spline: &Spline<f32>, x: f32, a: Time, b: Time, a_above: bool) -> Time {
+  let mut lo = a;
+  let mut hi = b;
+
+  for _ in 0..REFINE_ITERATIONS {
+    let mid = (lo + hi) * 0.5;
+
+    if mid <= lo || mid >= hi {
+      break;
+    }
+
+    match spline.sample_inclusive(mid) {
+      Some(v) if (v > x) == a_above => lo = mid,
+      _ => hi = mid
+    }
+  }
+
+  hi
+}
+
+// Find an extremum in [a, c] by golden-section search.
+fn refine_extremum(spline: &Spline<f32>, a: Time, c: Time, kind: ExtremumKind) -> (Time, f32) {
+  let inv_phi = 0.5 * (5f32.sqrt() - 1.);
+
+  // value to minimize
+  let cost = |t: Time| {
+    let v = spline.sample_inclusive(t).unwrap_or(0.);
+
+    match kind {
+      ExtremumKind::Minimum => v,
+      ExtremumKind::Maximum => -v
+    }
+  };
+
+  let mut lo = a;
+  let mut hi = c;
+
+  for _ in 0..REFINE_ITERATIONS {
+    let t0 = hi - (hi - lo) * inv_phi;
+    let t1 = lo + (hi - lo) * inv_phi;
+
+    if cost(t0) < cost(t1) {
+      hi = t1;
+    } else {
+      lo = t0;
+    }
+  }
+
+  let t = (lo + hi) * 0.5;
+  (t, spline.sample_inclusive(t).unwrap_or(0.))
+}
@@ -0,0 +1,262 @@
+//! Spline simplification.
+//!
+//! Recorded and baked splines often have way more keys than needed. This module removes keys from
+//! splines while staying within a given error tolerance.
+
+use cgmath::InnerSpace;
+use std::f32;
+
+use anim::spline::{Handle, Interpolate, Interpolation, Key, Spline, SplineSampler, Time};
+use linear::{Quat, Scale, V2, V3, V4};
+
+/// Types which values can be compared by distance.
+///
+/// It’s used to measure how far a simplified spline is from the original one.
+pub trait Metric {
+  /// Distance between two values.
+  fn distance(a: Self, b: Self) -> f32;
+}
+
+impl Metric for f32 {
+  fn distance(a: Self, b: Self) -> f32 {
+    (a - b).abs()
+  }
+}
+
+impl Metric for V2<f32> {
+  fn distance(a: Self, b: Self) -> f32 {
+    (a - b).magnitude()
+  }
+}
+
+impl Metric for V3<f32> {
+  fn distance(a: Self, b: Self) -> f32 {
+    (a - b).magnitude()
+  }
+}
+
+impl Metric for V4<f32> {
+  fn distance(a: Self, b: Self) -> f32 {
+    (a - b).magnitude()
+  }
+}
+
+/// The distance between two quaternions is the angle (in radians) of the rotation between them.
+impl Metric for Quat<f32> {
+  fn distance(a: Self, b: Self) -> f32 {
+    2. * a.dot(b).abs().min(1.).acos()
+  }
+}
+
+impl Metric for Scale {
+  fn distance(a: Self, b: Self) -> f32 {
+    V3::new(a.x - b.x, a.y - b.y, a.z - b.z).magnitude()
+  }
+}
+
+// Number of points measured inside every segment of the original spline, on top of its keys.
+const SUBDIVISIONS: usize = 4;
+
+/// Simplify a spline by removing keys while staying within an error tolerance.
+///
+/// This is a Ramer-Douglas-Peucker-like algorithm adapted to time-value curves: we start off with
+/// the first and last keys and keep adding the key where the simplified spline is the farthest from
+/// the original one until the error is below `tolerance` everywhere. The error is measured at the
+/// original keys and at a few evenly spaced points between them.
+///
+/// Kept keys keep their time and value and all use `interpolation`, whatever their original
+/// interpolation mode – except TCB keys simplified with `Interpolation::KochanekBartels`, which
+/// keep their own parameters. The simplified spline is then refit to the original one where
+/// `interpolation` allows it:
+///
+///   - with `Interpolation::Bezier`, handles are put a third of the way in time and their values
+///     are fit to the original spline in the least-squares sense; quaternions are fit
+///     component-wise, then normalized;
+///   - with `Interpolation::MonotoneCubic`, `Interpolation::CatmullRom` and
+///     `Interpolation::KochanekBartels`, tangents are computed from the kept keys, so there’s
+///     nothing more to fit;
+///   - other modes have no shape to fit.
+///
+/// With `Interpolation::CatmullRom` and `Interpolation::KochanekBartels`, the second and
+/// second-to-last keys are always kept so that the simplified spline can be sampled wherever the
+/// original one can.
+///
+/// Places where the original spline cannot be sampled are not measured; places where only the
+/// simplified spline cannot be sampled count as an infinite error.
+pub fn simplify<T>(spline: &Spline<T>, interpolation: Interpolation, tolerance: f32) -> Spline<T>
+    where T: Interpolate + Metric {
+  let keys = spline.keys();
+  let len = keys.len();
+
+  // indices of the kept keys, sorted
+  let mut kept: Vec<usize> = if len < 2 { (0..len).collect() } else { vec![0, len - 1] };
+
+  // those modes need a key before and two keys after every segment
+  match interpolation {
+    Interpolation::CatmullRom | Interpolation::KochanekBartels { .. } if len > 2 => {
+      kept = vec![0, 1, len - 2, len - 1];
+      kept.dedup();
+    },
+    _ => ()
+  }
+
+  loop {
+    let candidate = refit(spline, &kept, interpolation);
+
+    if kept.len() == len {
+      return candidate;
+    }
+
+    match farthest_key(spline, &candidate, &kept) {
+      Some((i, error)) if error > tolerance => {
+        let at = kept.binary_search(&i).unwrap_err();
+        kept.insert(at, i);
+      },
+      _ => return candidate
+    }
+  }
+}
+
+// Build a spline out of a subset of the keys of another one and fit it to the original one.
+fn refit<T>(spline: &Spline<T>, kept: &[usize], interpolation: Interpolation) -> Spline<T> where T: Interpolate {
+  let keys = spline.keys();
+  let mut refit_keys: Vec<_> = kept.iter().map(|&i| {
+    Key::new(keys[i].t, keys[i].value, kept_interpolation(keys[i].interpolation, interpolation))
+  }).collect();
+
+  if let Interpolation::Bezier = interpolation {
+    for (k, w) in kept.windows(2).enumerate() {
+      if let Some((h0, h1)) = fit_bezier(spline, w[0], w[1]) {
+        refit_keys[k].out_handle = Some(h0);
+        refit_keys[k + 1].in_handle = Some(h1);
+      }
+    }
+  }
+
+  let mut refit = Spline::from_keys(refit_keys);
+  refit.set_extrapolation(spline.pre_extrapolation(), spline.post_extrapolation());
+  refit
+}
+
+// Interpolation of a kept key, given its original one and the one asked for.
+fn kept_interpolation(original: Interpolation, interpolation: Interpolation) -> Interpolation {
+  match (original, interpolation) {
+    (Interpolation::KochanekBartels { .. }, Interpolation::KochanekBartels { .. }) => original,
+    _ => interpolation
+  }
+}
+
+// Handles of a Bézier segment going from the key a to the key b of a spline, fitting the spline
+// between those keys in the least-squares sense.
+//
+// Handles are put a third of the way in time, so that the time of the curve is linear in its
+// parameter u. The value is then a linear combination of the Bernstein polynomials of u, and only
+// the weights of the handles are unknown, which boils down to a 2×2 linear system.
+fn fit_bezier<T>(spline: &Spline<T>, a: usize, b: usize) -> Option<(Handle<T>, Handle<T>)> where T: Interpolate {
+  let keys = spline.keys();
+  let (t0, v0) = (keys[a].t, keys[a].value);
+  let (t1, v1) = (keys[b].t, keys[b].value);
+  let dt = t1 - t0;
+
+  if dt <= 0. {
+    return None;
+  }
+
+  let zero = v0 - v0;
+  let (mut a11, mut a12, mut a22) = (0., 0., 0.);
+  let (mut r1, mut r2) = (zero, zero);
+
+  for t in measured_times(keys, a, b) {
+    let y = match spline.sample_inclusive(t) {
+      Some(y) => y,
+      None => continue
+    };
+
+    let u = (t - t0) / dt;
+    let iu = 1. - u;
+    let (b0, b1, b2, b3) = (iu * iu * iu, 3. * iu * iu * u, 3. * iu * u * u, u * u * u);
+    let r = y - v0 * b0 - v1 * b3;
+
+    a11 += b1 * b1;
+    a12 += b1 * b2;
+    a22 += b2 * b2;
+    r1 = r1 + r * b1;
+    r2 = r2 + r * b2;
+  }
+
+  let det = a11 * a22 - a12 * a12;
+
+  // not enough samples inside the segment to fit both handles
+  if det <= 1e-9 {
+    return None;
+  }
+
+  let p1 = T::project((r1 * a22 - r2 * a12) / det, zero, zero).0;
+  let p2 = T::project((r2 * a11 - r1 * a12) / det, zero, zero).0;
+
+  Some((Handle::new(t0 + dt / 3., p1), Handle::new(t1 - dt / 3., p2)))
+}
+
+// Times at which a spline is measured from its key a to its key b, both included: the keys and
+// SUBDIVISIONS evenly spaced points inside every segment.
+fn measured_times<T>(keys: &[Key<T>], a: usize, b: usize) -> Vec<Time> {
+  let mut times = Vec::with_capacity((b - a) * (SUBDIVISIONS + 1) + 1);
+
+  for i in a..b {
+    times.push(keys[i].t);
+    times.extend(inside(keys, i));
+  }
+
+  times.push(keys[b].t);
+  times
+}
+
+// SUBDIVISIONS evenly spaced times inside the segment starting at the key i.
+fn inside<T>(keys: &[Key<T>], i: usize) -> Vec<Time> {
+  let (t0, t1) = (keys[i].t, keys[i + 1].t);
+
+  (1..SUBDIVISIONS + 1).map(|k| t0 + (t1 - t0) * k as Time / (SUBDIVISIONS + 1) as Time).collect()
+}
+
+// Find the key which is not kept yet and around which the candidate spline is the farthest from
+// the original one. The error around a key is the maximum of the errors at the key and inside the
+// segments on both sides of it.
+fn farthest_key<T>(spline: &Spline<T>, candidate: &Spline<T>, kept: &[usize]) -> Option<(usize, f32)>
+    where T: Interpolate + Metric {
+  let keys = spline.keys();
+  let mut original_sampler = SplineSampler::new(spline);
+  let mut candidate_sampler = SplineSampler::new(candidate);
+  let mut error_at = |t: Time| {
+    match (original_sampler.sample(t), candidate_sampler.sample(t)) {
+      (Some(a), Some(b)) => T::distance(a, b),
+      (Some(_), None) => f32::INFINITY,
+      (None, _) => 0.
+    }
+  };
+
+  let mut farthest = None;
+  let mut prev_segment_error = 0.;
+
+  for i in 0..keys.len() {
+    let key_error = error_at(keys[i].t);
+    let next_segment_error = if i + 1 < keys.len() {
+      inside(keys, i).into_iter().map(&mut error_at).fold(0., |m: f32, e| m.max(e))
+    } else {
+      0.
+    };
+    let error = key_error.max(prev_segment_error).max(next_segment_error);
+
+    prev_segment_error = next_segment_error;
+
+    if kept.binary_search(&i).is_ok() {
+      continue;
+    }
+
+    farthest = match farthest {
+      Some((_, e)) if e >= error => farthest,
+      _ => Some((i, error))
+    };
+  }
+
+  farthest
+}
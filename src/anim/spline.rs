@@ -1,16 +1,19 @@
 use cgmath::{BaseFloat, InnerSpace};
+use serde::Serialize;
 use serde::de::DeserializeOwned;
-use serde_json::from_reader;
+use serde_json::{from_reader, to_writer_pretty};
 use std::f32::consts;
 use std::fs::File;
 use std::fmt;
 use std::hash;
 use std::marker::PhantomData;
+use std::mem;
 use std::ops::{Add, Div, Mul, Sub};
 use std::path::{Path, PathBuf};
 
+use anim::easing::Easing;
 use linear::{Scale, Quat, V2, V3, V4};
-use sys::resource::{CacheKey, Load, LoadError, LoadResult, Store, StoreKey};
+use sys::resource::{CacheKey, Load, LoadError, LoadResult, Save, SaveError, Store, StoreKey};
 
 /// Time used as sampling type in splines.
 pub type Time = f32;
@@ -27,7 +30,15 @@ pub struct Key<T> {
   pub value: T,
   /// Interpolation mode.
   #[serde(default)]
-  pub interpolation: Interpolation
+  pub interpolation: Interpolation,
+  /// Incoming Bézier handle, used by the segment ending at this key if it’s
+  /// `Interpolation::Bezier`.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub in_handle: Option<Handle<T>>,
+  /// Outgoing Bézier handle, used by the segment starting at this key if it’s
+  /// `Interpolation::Bezier`.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub out_handle: Option<Handle<T>>
 }
 
 impl<T> Key<T> {
@@ -36,7 +47,42 @@ impl<T> Key<T> {
     Key {
       t: t,
       value: value,
-      interpolation: interpolation
+      interpolation: interpolation,
+      in_handle: None,
+      out_handle: None
+    }
+  }
+
+  /// Create a new key with Bézier handles.
+  pub fn new_with_handles(t: Time, value: T, interpolation: Interpolation, in_handle: Option<Handle<T>>, out_handle: Option<Handle<T>>) -> Self {
+    Key {
+      t: t,
+      value: value,
+      interpolation: interpolation,
+      in_handle: in_handle,
+      out_handle: out_handle
+    }
+  }
+}
+
+/// Bézier tangent handle.
+///
+/// A handle lives in the same time-value space as keys. Its time is clamped to the segment it
+/// belongs to so that the curve always moves forward in time.
+#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
+pub struct Handle<T> {
+  /// Time of the handle.
+  pub t: Time,
+  /// Value of the handle.
+  pub value: T
+}
+
+impl<T> Handle<T> {
+  /// Create a new handle.
+  pub fn new(t: Time, value: T) -> Self {
+    Handle {
+      t: t,
+      value: value
     }
   }
 }
@@ -60,7 +106,48 @@ pub enum Interpolation {
   Cosine,
   /// Catmull-Rom interpolation.
   #[serde(rename = "catmull_rom")]
-  CatmullRom
+  CatmullRom,
+  /// Cubic Bézier interpolation between a key and the next one, using the outgoing handle of the
+  /// key and the incoming handle of the next one.
+  ///
+  /// The curve is solved in time, so keys are always reached at their exact time. A missing handle
+  /// is set to its key; as the time and the value of the curve are then weighted alike, a segment
+  /// without any handle is linear. Put handles a third of the way in time with the value of their
+  /// key to get an ease-in / ease-out shape.
+  #[serde(rename = "bezier")]
+  Bezier,
+  /// Kochanek-Bartels (TCB) interpolation.
+  ///
+  /// It’s a generalization of Catmull-Rom: with all three parameters set to `0` and evenly spaced
+  /// keys, it’s the same as a Catmull-Rom spline. With unevenly spaced keys, tangents are scaled by
+  /// the durations of the segments around the key so that the speed is continuous across it,
+  /// which `Interpolation::CatmullRom` doesn’t do. The parameters shape the tangents at the key;
+  /// the tangents at the next key use its own parameters if it’s a TCB key too, `0` otherwise. As
+  /// with Catmull-Rom, you need a key before and two keys after the current one.
+  #[serde(rename = "tcb")]
+  KochanekBartels {
+    /// Tension: `1` sharpens the curve around the key, `-1` rounds it.
+    #[serde(default)]
+    tension: f32,
+    /// Continuity: anything but `0` makes a corner at the key.
+    #[serde(default)]
+    continuity: f32,
+    /// Bias: `1` makes the curve overshoot after the key, `-1` before it.
+    #[serde(default)]
+    bias: f32
+  },
+  /// Monotone cubic interpolation (Fritsch-Carlson).
+  ///
+  /// The curve is smooth but never overshoots: it stays within the values of the keys around it
+  /// and is flat at local extrema. Unlike Catmull-Rom, it doesn’t require any extra key, so the
+  /// first and last segments can be sampled too.
+  #[serde(rename = "monotone_cubic")]
+  MonotoneCubic,
+  /// Interpolation between a key and the next one using an easing function.
+  ///
+  /// Some easing functions (back, elastic) overshoot the values of the keys.
+  #[serde(rename = "ease")]
+  Ease(Easing)
 }
 
 impl Default for Interpolation {
@@ -70,61 +157,444 @@ impl Default for Interpolation {
   }
 }
 
+/// Extrapolation mode.
+///
+/// Determine how a spline behaves before its first key (*pre* extrapolation) or after its last key
+/// (*post* extrapolation). If all the keys are at the same time – which is the case of a single
+/// key – every mode behaves as `Extrapolation::Constant`.
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Extrapolation {
+  /// Hold the value of the first or last key.
+  #[serde(rename = "constant")]
+  Constant,
+  /// Continue with the derivative of the spline at the first or last key. If the spline can’t be
+  /// sampled there – e.g. with `Interpolation::CatmullRom` – the slope between the first two or
+  /// last two keys is used instead.
+  #[serde(rename = "linear")]
+  Linear,
+  /// Repeat the spline over and over.
+  #[serde(rename = "repeat")]
+  Repeat,
+  /// Repeat the spline, playing it backwards every other cycle.
+  #[serde(rename = "ping_pong")]
+  PingPong,
+  /// Repeat the spline, offsetting every cycle by the difference between the last and the first
+  /// keys’ values so that cycles chain continuously.
+  #[serde(rename = "offset_repeat")]
+  OffsetRepeat
+}
+
 /// Spline curve used to provide interpolation between control points (keys).
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Spline<T> {
   keys: Vec<Key<T>>,
+  /// Extrapolation used before the first key.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pre: Option<Extrapolation>,
+  /// Extrapolation used after the last key.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  post: Option<Extrapolation>
 }
 
 impl<T> Spline<T> {
   /// Create a new spline out of keys. The keys don’t have to be sorted because they’re sorted by
   /// this function.
+  ///
+  /// The spline has no extrapolation.
   pub fn from_keys(mut keys: Vec<Key<T>>) -> Self {
     keys.sort_by(|k0, k1| k0.t.partial_cmp(&k1.t).unwrap());
 
     Spline {
-      keys: keys
+      keys: keys,
+      pre: None,
+      post: None
+    }
+  }
+
+  /// Keys of the spline, sorted by time.
+  pub fn keys(&self) -> &[Key<T>] {
+    &self.keys
+  }
+
+  /// Take the keys out of the spline.
+  pub fn into_keys(self) -> Vec<Key<T>> {
+    self.keys
+  }
+
+  /// Insert a key in the spline, keeping the keys sorted.
+  ///
+  /// If keys already exist at the same time, the key is inserted after them.
+  ///
+  /// # Return
+  ///
+  /// The index of the inserted key.
+  pub fn insert_key(&mut self, key: Key<T>) -> Result<usize, SplineError> {
+    check_time(key.t)?;
+
+    let i = upper_bound(&self.keys, key.t);
+    self.keys.insert(i, key);
+
+    Ok(i)
+  }
+
+  /// Remove the key at the given index.
+  pub fn remove_key(&mut self, i: usize) -> Result<Key<T>, SplineError> {
+    if i >= self.keys.len() {
+      return Err(SplineError::OutOfBounds(i));
+    }
+
+    Ok(self.keys.remove(i))
+  }
+
+  /// Replace the key at the given index, keeping the keys sorted.
+  ///
+  /// # Return
+  ///
+  /// The new index of the key.
+  pub fn replace_key(&mut self, i: usize, key: Key<T>) -> Result<usize, SplineError> {
+    check_time(key.t)?;
+    self.remove_key(i)?;
+    self.insert_key(key)
+  }
+
+  /// Move the key at the given index to a new time, keeping the keys sorted. The Bézier handles of
+  /// the key are moved along.
+  ///
+  /// # Return
+  ///
+  /// The new index of the key.
+  pub fn move_key(&mut self, i: usize, t: Time) -> Result<usize, SplineError> {
+    check_time(t)?;
+
+    let mut key = self.remove_key(i)?;
+    let dt = t - key.t;
+
+    key.t = t;
+
+    if let Some(ref mut h) = key.in_handle {
+      h.t += dt;
+    }
+
+    if let Some(ref mut h) = key.out_handle {
+      h.t += dt;
+    }
+
+    self.insert_key(key)
+  }
+
+  /// Retime the whole spline by mapping the time of every key and handle.
+  ///
+  /// The mapping must be monotonic over the keys. A decreasing mapping reverses the spline: the
+  /// handles of every key swap sides and every segment keeps its interpolation mode. Asymmetric
+  /// modes – step thresholds, easings – are not mirrored though.
+  ///
+  /// If the mapping yields an invalid time or is not monotonic, the spline is left untouched.
+  pub fn retime<F>(&mut self, f: F) -> Result<(), SplineError> where F: Fn(Time) -> Time {
+    // check the new times before touching anything
+    for key in &self.keys {
+      check_time(f(key.t))?;
+
+      if let Some(ref h) = key.in_handle {
+        check_time(f(h.t))?;
+      }
+
+      if let Some(ref h) = key.out_handle {
+        check_time(f(h.t))?;
+      }
+    }
+
+    let times: Vec<_> = self.keys.iter().map(|key| f(key.t)).collect();
+    let increasing = times.windows(2).all(|w| w[0] <= w[1]);
+
+    if !increasing && !times.windows(2).all(|w| w[0] >= w[1]) {
+      return Err(SplineError::NonMonotonicMap);
+    }
+
+    for key in &mut self.keys {
+      key.t = f(key.t);
+
+      if let Some(ref mut h) = key.in_handle {
+        h.t = f(h.t);
+      }
+
+      if let Some(ref mut h) = key.out_handle {
+        h.t = f(h.t);
+      }
+    }
+
+    if !increasing {
+      self.keys.reverse();
+
+      for key in &mut self.keys {
+        mem::swap(&mut key.in_handle, &mut key.out_handle);
+      }
+
+      // every segment is now started by its other key
+      let len = self.keys.len();
+      let first = self.keys[0].interpolation;
+
+      for i in 0..len - 1 {
+        self.keys[i].interpolation = self.keys[i + 1].interpolation;
+      }
+
+      self.keys[len - 1].interpolation = first;
+    }
+
+    Ok(())
+  }
+
+  /// Check that every key has what its interpolation mode requires to be sampled.
+  ///
+  /// For instance, an `Interpolation::CatmullRom` key needs a key before it and two keys after
+  /// it. Editing a spline doesn’t perform this check on its own since a spline is often in an
+  /// intermediate state while being edited.
+  pub fn validate(&self) -> Result<(), SplineError> {
+    let len = self.keys.len();
+
+    // the last key doesn’t start any segment, so its interpolation mode is never used
+    for (i, key) in self.keys.iter().enumerate().take(len.saturating_sub(1)) {
+      match key.interpolation {
+        Interpolation::CatmullRom | Interpolation::KochanekBartels { .. } if i == 0 || i + 2 >= len => {
+          return Err(SplineError::MissingNeighbours(i));
+        },
+        _ => ()
+      }
     }
+
+    Ok(())
+  }
+
+  /// Extrapolation used before the first key, if any.
+  pub fn pre_extrapolation(&self) -> Option<Extrapolation> {
+    self.pre
+  }
+
+  /// Extrapolation used after the last key, if any.
+  pub fn post_extrapolation(&self) -> Option<Extrapolation> {
+    self.post
+  }
+
+  /// Change the extrapolation used before the first key and after the last one. `None` makes
+  /// sampling outside of the keys’ range fail.
+  pub fn set_extrapolation(&mut self, pre: Option<Extrapolation>, post: Option<Extrapolation>) {
+    self.pre = pre;
+    self.post = post;
   }
 
   /// Sample a spline at a given time.
   ///
   /// # Return
   ///
-  /// `None` if you try to sample a value at a time that has no key associated with. That can also
-  /// happen if you try to sample between two keys with a specific interpolation mode that make the
-  /// sampling impossible. For instance, `Interpolate::CatmullRom` requires *four* keys. If you’re
-  /// near the beginning of the spline or its end, ensure you have enough keys around to make the
-  /// sampling.
+  /// `None` if you try to sample a value at a time that has no key associated with and that the
+  /// spline has no extrapolation for. That can also happen if you try to sample between two keys
+  /// with a specific interpolation mode that make the sampling impossible. For instance,
+  /// `Interpolate::CatmullRom` requires *four* keys. If you’re near the beginning of the spline or
+  /// its end, ensure you have enough keys around to make the sampling.
   pub fn sample(&self, t: Time) -> Option<T> where T: Interpolate {
+    self.sample_order(t, Order::Value, search_lower_cp)
+  }
+
+  /// Sample a spline at a given time, including at the end of its segments.
+  ///
+  /// It’s the same as `Spline::sample`, but at the time of a key where that fails – e.g. the last
+  /// key without post extrapolation, or a key starting a segment that cannot be sampled – the
+  /// segment ending at the key is sampled instead. A single key is its own end.
+  pub fn sample_inclusive(&self, t: Time) -> Option<T> where T: Interpolate {
+    self.sample(t).or_else(|| {
+      let keys = &self.keys;
+
+      // keys[i - 1] is the first key at t, if any
+      let mut i = upper_bound(keys, t);
+
+      if i == 0 || keys[i - 1].t != t {
+        return None;
+      }
+
+      while i > 1 && keys[i - 2].t == t {
+        i -= 1;
+      }
+
+      if i > 1 {
+        self.sample_segment(i - 2, t, Order::Value)
+      } else if keys.len() == 1 {
+        Some(keys[0].value)
+      } else {
+        None
+      }
+    })
+  }
+
+  /// Sample the segment starting at the key `i` at a given time, regardless of the keys’ range and
+  /// of extrapolation. The time is expected to lie within the segment, its end included.
+  ///
+  /// # Return
+  ///
+  /// `None` if there’s no segment starting at `i`, if it’s empty or if it cannot be sampled – see
+  /// `Spline::sample`.
+  pub fn sample_in_segment(&self, i: usize, t: Time) -> Option<T> where T: Interpolate {
+    match (self.keys.get(i), self.keys.get(i + 1)) {
+      (Some(cp0), Some(cp1)) if cp1.t > cp0.t => self.sample_segment(i, t, Order::Value),
+      _ => None
+    }
+  }
+
+  /// Sample the derivative of a spline at a given time.
+  ///
+  /// The derivative is computed analytically from the interpolation mode of the segment and is
+  /// expressed in value per unit of time.
+  ///
+  /// # Return
+  ///
+  /// `None` in the same situations as `Spline::sample`. `Interpolation::Step` segments yield a
+  /// null derivative.
+  pub fn sample_derivative(&self, t: Time) -> Option<T> where T: Interpolate {
+    self.sample_order(t, Order::First, search_lower_cp)
+  }
+
+  /// Sample the second derivative of a spline at a given time.
+  ///
+  /// # Return
+  ///
+  /// `None` in the same situations as `Spline::sample`.
+  pub fn sample_second_derivative(&self, t: Time) -> Option<T> where T: Interpolate {
+    self.sample_order(t, Order::Second, search_lower_cp)
+  }
+
+  // Sample a spline (or one of its derivatives), extrapolating if needed. find is used to look up
+  // the segment to sample.
+  fn sample_order<F>(&self, t: Time, order: Order, find: F) -> Option<T>
+      where T: Interpolate,
+            F: FnOnce(&[Key<T>], Time) -> Option<usize> {
     let keys = &self.keys;
-    let i = search_lower_cp(keys, t);
+    let len = keys.len();
 
-    let i = match i {
-      Some(i) => i,
-      None => return None
+    if len == 0 {
+      return None;
+    }
+
+    if len == 1 {
+      // a single key is held by every extrapolation mode, as when all the keys are at the same time
+      let key = &keys[0];
+      let extrapolation = if t < key.t { self.pre } else { self.post };
+
+      return extrapolation.map(|_| {
+        match order {
+          Order::Value => key.value,
+          _ => key.value - key.value
+        }
+      });
+    }
+
+    let extrapolation = if t < keys[0].t {
+      self.pre
+    } else if t >= keys[len - 1].t {
+      self.post
+    } else {
+      return find(keys, t).and_then(|i| self.sample_segment(i, t, order));
     };
 
+    extrapolation.and_then(|extrapolation| self.extrapolate(extrapolation, t, order, find))
+  }
+
+  // Sample a spline (or one of its derivatives) outside of its keys’ range. The spline must have at
+  // least two keys.
+  fn extrapolate<F>(&self, extrapolation: Extrapolation, t: Time, order: Order, find: F) -> Option<T>
+      where T: Interpolate,
+            F: FnOnce(&[Key<T>], Time) -> Option<usize> {
+    let keys = &self.keys;
+    let len = keys.len();
+    let first = &keys[0];
+    let last = &keys[len - 1];
+    let period = last.t - first.t;
+
+    // key at which we leave the spline and the segment it belongs to
+    let (boundary, segment) = if t < first.t { (first, 0) } else { (last, len - 2) };
+
+    // all the keys are at the same time: there’s nothing to repeat nor any slope to follow
+    let extrapolation = if period > 0. { extrapolation } else { Extrapolation::Constant };
+
+    // time wrapped in the spline’s range and the cycle it belongs to
+    let cycle = ((t - first.t) / period).floor();
+    let local = (t - first.t) - cycle * period;
+
+    match extrapolation {
+      Extrapolation::Constant => {
+        Some(match order {
+          Order::Value => boundary.value,
+          _ => boundary.value - boundary.value
+        })
+      },
+      Extrapolation::Linear => {
+        // Catmull-Rom and TCB segments can’t be sampled at the ends of the spline; follow the slope
+        // of the boundary segment instead
+        let d = self.sample_segment(segment, boundary.t, Order::First).unwrap_or_else(|| {
+          let (cp0, cp1) = (&keys[segment], &keys[segment + 1]);
+          (cp1.value - cp0.value) / (cp1.t - cp0.t)
+        });
+
+        Some(match order {
+          Order::Value => boundary.value + d * (t - boundary.t),
+          Order::First => d,
+          Order::Second => d - d
+        })
+      },
+      Extrapolation::Repeat => self.sample_closed(first.t + local, order, find),
+      Extrapolation::PingPong => {
+        if (cycle as i64) % 2 == 0 {
+          self.sample_closed(first.t + local, order, find)
+        } else {
+          // playing backwards flips the first derivative only
+          self.sample_closed(last.t - local, order, find).map(|x| if order == Order::First { x * -1. } else { x })
+        }
+      },
+      Extrapolation::OffsetRepeat => {
+        self.sample_closed(first.t + local, order, find).map(|x| {
+          match order {
+            Order::Value => x + (last.value - first.value) * cycle,
+            _ => x
+          }
+        })
+      }
+    }
+  }
+
+  // Sample a spline (or one of its derivatives) in the closed range of its keys, without
+  // extrapolation. Times slightly out of the range are sampled on the closest segment.
+  fn sample_closed<F>(&self, t: Time, order: Order, find: F) -> Option<T>
+      where T: Interpolate,
+            F: FnOnce(&[Key<T>], Time) -> Option<usize> {
+    let keys = &self.keys;
+    let i = find(keys, t).unwrap_or(if t < keys[0].t { 0 } else { keys.len() - 2 });
+
+    self.sample_segment(i, t, order)
+  }
+
+  // Sample the segment starting at the key i (or one of its derivatives).
+  fn sample_segment(&self, i: usize, t: Time, order: Order) -> Option<T> where T: Interpolate {
+    let keys = &self.keys;
     let cp0 = &keys[i];
+    let cp1 = &keys[i+1];
+    let dt = cp1.t - cp0.t;
+    let nt = normalize_time(t, cp0, cp1);
 
     match cp0.interpolation {
       Interpolation::Step(threshold) => {
-        let cp1 = &keys[i+1];
-        let nt = normalize_time(t, cp0, cp1);
-        Some(if nt < threshold { cp0.value } else { cp1.value })
+        let value = if nt < threshold { cp0.value } else { cp1.value };
+
+        Some(match order {
+          Order::Value => value,
+          _ => value - value
+        })
       },
       Interpolation::Linear => {
-        let cp1 = &keys[i+1];
-        let nt = normalize_time(t, cp0, cp1);
-
-        Some(Interpolate::lerp(cp0.value, cp1.value, nt))
+        Some(lerp_order(cp0.value, cp1.value, nt, 1. / dt, 0., order))
       },
       Interpolation::Cosine => {
-        let cp1 = &keys[i+1];
-        let nt = normalize_time(t, cp0, cp1);
         let cos_nt = (1. - f32::cos(nt * consts::PI)) * 0.5;
+        let d_cos_nt = f32::sin(nt * consts::PI) * consts::PI * 0.5 / dt;
+        let dd_cos_nt = f32::cos(nt * consts::PI) * consts::PI * consts::PI * 0.5 / (dt * dt);
 
-        Some(Interpolate::lerp(cp0.value, cp1.value, cos_nt))
+        Some(lerp_order(cp0.value, cp1.value, cos_nt, d_cos_nt, dd_cos_nt, order))
       },
       Interpolation::CatmullRom => {
         // We need at least four points for Catmull Rom; ensure we have them, otherwise, return
@@ -132,17 +602,122 @@ impl<T> Spline<T> {
         if i == 0 || i >= keys.len() - 2 {
           None
         } else {
-          let cp1 = &keys[i+1];
           let cpm0 = &keys[i-1];
           let cpm1 = &keys[i+2];
-          let nt = normalize_time(t, cp0, cp1);
-
-          Some(Interpolate::cubic_hermite((cpm0.value, cpm0.t), (cp0.value, cp0.t), (cp1.value, cp1.t), (cpm1.value, cpm1.t), nt))
+          let x = (cpm0.value, cpm0.t);
+          let a = (cp0.value, cp0.t);
+          let b = (cp1.value, cp1.t);
+          let y = (cpm1.value, cpm1.t);
+
+          Some(match order {
+            Order::Value => T::cubic_hermite(x, a, b, y, nt),
+            Order::First => T::cubic_hermite_derivative(x, a, b, y, nt) / dt,
+            Order::Second => T::cubic_hermite_second_derivative(x, a, b, y, nt) / (dt * dt)
+          })
+        }
+      },
+      Interpolation::Bezier => {
+        let (h0, h1) = bezier_handles(cp0, cp1);
+        let u = bezier_solve_time(cp0.t, h0.t, h1.t, cp1.t, t);
+
+        match order {
+          Order::Value => Some(bezier(cp0.value, h0.value, h1.value, cp1.value, u)),
+          _ => {
+            // the time curve might have a null derivative at the very ends of the segment if the
+            // handles coincide with their keys; step slightly inside to use the limit instead
+            let u = u.max(1e-3).min(1. - 1e-3);
+            let (_, dx, ddx) = bezier_scalar(cp0.t, h0.t, h1.t, cp1.t, u);
+            let (db, ddb) = bezier_derivatives(cp0.value, h0.value, h1.value, cp1.value, u);
+
+            Some(match order {
+              Order::First => db / dx,
+              _ => (ddb * dx - db * ddx) / (dx * dx * dx)
+            })
+          }
         }
+      },
+      Interpolation::KochanekBartels { .. } | Interpolation::MonotoneCubic => {
+        self.hermite_tangents(i).map(|(m0, m1)| {
+          let hermite = |order| hermite_order(cp0.value, m0, cp1.value, m1, nt, dt, order);
+          let (value, d, dd) = T::project(hermite(Order::Value), hermite(Order::First), hermite(Order::Second));
+
+          match order {
+            Order::Value => value,
+            Order::First => d,
+            Order::Second => dd
+          }
+        })
+      },
+      Interpolation::Ease(easing) => {
+        let (ease_nt, d_ease_nt, dd_ease_nt) = easing.ease_with_derivatives(nt);
+
+        Some(lerp_order(cp0.value, cp1.value, ease_nt, d_ease_nt / dt, dd_ease_nt / (dt * dt), order))
       }
     }
   }
 
+  /// Tangents of the segment starting at the key `i`, for the interpolation modes relying on
+  /// cubic Hermite interpolation with computed tangents – `Interpolation::KochanekBartels` and
+  /// `Interpolation::MonotoneCubic`. Tangents are expressed for the normalized time of the segment.
+  ///
+  /// # Return
+  ///
+  /// `None` if the key uses another interpolation mode, is the last key or lacks the neighbours its
+  /// interpolation mode requires.
+  pub fn hermite_tangents(&self, i: usize) -> Option<(T, T)> where T: Interpolate {
+    let keys = &self.keys;
+
+    if i + 1 >= keys.len() {
+      return None;
+    }
+
+    let cp0 = &keys[i];
+    let cp1 = &keys[i+1];
+    let dt = cp1.t - cp0.t;
+
+    match cp0.interpolation {
+      Interpolation::KochanekBartels { tension, continuity, bias } => {
+        // same requirements as Catmull Rom
+        if i == 0 || i >= keys.len() - 2 {
+          None
+        } else {
+          let cpm0 = &keys[i-1];
+          let cpm1 = &keys[i+2];
+          let (_, m0) = tcb_tangents(cpm0, cp0, cp1, tension, continuity, bias);
+          let (tension1, continuity1, bias1) = tcb_parameters(&cp1.interpolation);
+          let (m1, _) = tcb_tangents(cp0, cp1, cpm1, tension1, continuity1, bias1);
+
+          Some((m0, m1))
+        }
+      },
+      Interpolation::MonotoneCubic => {
+        // slope of the segment; the first and last keys use it as tangent
+        let d = (cp1.value - cp0.value) / dt;
+
+        let m0 = if i > 0 {
+          let cpm0 = &keys[i-1];
+          let dtm = cp0.t - cpm0.t;
+
+          T::monotone_tangent((cp0.value - cpm0.value) / dtm, dtm, d, dt)
+        } else {
+          d
+        };
+
+        let m1 = if i + 2 < keys.len() {
+          let cpm1 = &keys[i+2];
+          let dtm = cpm1.t - cp1.t;
+
+          T::monotone_tangent(d, dt, (cpm1.value - cp1.value) / dtm, dtm)
+        } else {
+          d
+        };
+
+        Some((m0 * dt, m1 * dt))
+      },
+      _ => None
+    }
+  }
+
   /// Sample a spline at a given time with clamping.
   ///
   /// # Return
@@ -165,76 +740,300 @@ impl<T> Spline<T> {
 
     self.sample(t).unwrap()
   }
+
+  /// Sample a spline at a given time with clamping, without panicking.
+  ///
+  /// The spline is sampled as with `Spline::sample`. Where it cannot be, the time is clamped to the
+  /// nearest time it can be sampled at: before the first key or after the last one, the first or
+  /// last key is used – as with `Spline::clamped_sample` – and in a segment that cannot be sampled
+  /// – e.g. the first and last segments of a Catmull-Rom spline – the closest end of the closest
+  /// segment that can be sampled is used.
+  ///
+  /// # Return
+  ///
+  /// `None` if the spline has no key, or if `t` is within the keys’ range and no segment can be
+  /// sampled.
+  pub fn try_clamped_sample(&self, t: Time) -> Option<T> where T: Interpolate {
+    let keys = &self.keys;
+
+    let (first, last) = match (keys.first(), keys.last()) {
+      (Some(first), Some(last)) => (first, last),
+      _ => return None
+    };
+
+    if let Some(value) = self.sample(t) {
+      return Some(value);
+    }
+
+    if t <= first.t {
+      return Some(first.value);
+    } else if t >= last.t {
+      return Some(last.value);
+    }
+
+    let i = match search_lower_cp(keys, t) {
+      Some(i) => i,
+      None => return None
+    };
+
+    let sampleable = |j: &usize| self.sample_segment(*j, keys[*j].t, Order::Value).is_some();
+    let before = (0..i).rev().find(&sampleable);
+    let after = (i + 1..keys.len() - 1).find(&sampleable);
+
+    // sample at the end of the segment before or at the start of the segment after
+    let end_of = |b: usize| self.sample_segment(b, keys[b + 1].t, Order::Value);
+    let start_of = |a: usize| self.sample_segment(a, keys[a].t, Order::Value);
+
+    match (before, after) {
+      (Some(b), Some(a)) if t - keys[b + 1].t <= keys[a].t - t => end_of(b),
+      (_, Some(a)) => start_of(a),
+      (Some(b), None) => end_of(b),
+      (None, None) => None
+    }
+  }
+}
+
+/// Error that might occur while editing a spline.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SplineError {
+  /// There’s no key at the given index.
+  OutOfBounds(usize),
+  /// The time is not a finite number.
+  InvalidTime(Time),
+  /// The key at the given index lacks neighbours required by its interpolation mode.
+  MissingNeighbours(usize),
+  /// A time mapping doesn’t preserve nor reverse the order of the keys.
+  NonMonotonicMap
+}
+
+/// Spline sampler.
+///
+/// A sampler remembers the last segment it sampled, so that sequential sampling – e.g. during
+/// playback – finds the segment to sample in constant time. Random access falls back to a binary
+/// search.
+pub struct SplineSampler<'a, T> where T: 'a {
+  spline: &'a Spline<T>,
+  cursor: usize
+}
+
+impl<'a, T> SplineSampler<'a, T> where T: 'a {
+  /// Create a new sampler for a spline.
+  pub fn new(spline: &'a Spline<T>) -> Self {
+    SplineSampler {
+      spline: spline,
+      cursor: 0
+    }
+  }
+
+  /// Sample the spline at a given time. See `Spline::sample` for further details.
+  pub fn sample(&mut self, t: Time) -> Option<T> where T: Interpolate {
+    let cursor = &mut self.cursor;
+    self.spline.sample_order(t, Order::Value, |keys, t| search_lower_cp_from(keys, t, cursor))
+  }
+
+  /// Sample the derivative of the spline at a given time. See `Spline::sample_derivative` for
+  /// further details.
+  pub fn sample_derivative(&mut self, t: Time) -> Option<T> where T: Interpolate {
+    let cursor = &mut self.cursor;
+    self.spline.sample_order(t, Order::First, |keys, t| search_lower_cp_from(keys, t, cursor))
+  }
+
+  /// Sample the second derivative of the spline at a given time. See
+  /// `Spline::sample_second_derivative` for further details.
+  pub fn sample_second_derivative(&mut self, t: Time) -> Option<T> where T: Interpolate {
+    let cursor = &mut self.cursor;
+    self.spline.sample_order(t, Order::Second, |keys, t| search_lower_cp_from(keys, t, cursor))
+  }
 }
 
-#[derive(Eq, PartialEq)]
-pub struct SplineKey<T> {
-  pub key: String, 
-  _t: PhantomData<*const T>
+/// Key of a spline-based resource in the store – splines, but also discrete splines, transform
+/// splines, noise modifiers and so on. `R` is the type of the resource.
+pub struct SplineResourceKey<R> {
+  pub key: String,
+  _r: PhantomData<*const R>
 }
 
-impl<T> SplineKey<T> {
+impl<R> SplineResourceKey<R> {
   pub fn new(key: &str) -> Self {
-    SplineKey {
+    SplineResourceKey {
       key: key.to_owned(),
-      _t: PhantomData
+      _r: PhantomData
     }
   }
 }
 
-impl<T> Clone for SplineKey<T> {
+impl<R> Clone for SplineResourceKey<R> {
   fn clone(&self) -> Self {
-    SplineKey {
+    SplineResourceKey {
       key: self.key.clone(),
       ..*self
     }
   }
 }
 
-impl<T> fmt::Debug for SplineKey<T> {
+impl<R> PartialEq for SplineResourceKey<R> {
+  fn eq(&self, rhs: &Self) -> bool {
+    self.key == rhs.key
+  }
+}
+
+impl<R> Eq for SplineResourceKey<R> {}
+
+impl<R> fmt::Debug for SplineResourceKey<R> {
   fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
     self.key.fmt(f)
   }
 }
 
-impl<T> hash::Hash for SplineKey<T> {
+impl<R> hash::Hash for SplineResourceKey<R> {
   fn hash<H>(&self, hasher: &mut H) where H: hash::Hasher {
     self.key.hash(hasher)
   }
 }
 
-impl<T> CacheKey for SplineKey<T> where T: 'static {
-  type Target = Spline<T>;
+impl<R> CacheKey for SplineResourceKey<R> where R: 'static {
+  type Target = R;
 }
 
-impl<T> StoreKey for SplineKey<T> where T: 'static {
+impl<R> StoreKey for SplineResourceKey<R> where R: 'static {
   fn key_to_path(&self) -> PathBuf {
     self.key.clone().into()
   }
 }
 
+/// Key of a spline in the store.
+pub type SplineKey<T> = SplineResourceKey<Spline<T>>;
+
+impl<T> Spline<T> {
+  /// Turn a `SplineManifest` into a spline, converting the keys’ values from their deserialized
+  /// form.
+  pub fn from_manifest(manifest: SplineManifest<T::Deserialized>) -> Self where T: SplineDeserializerAdapter {
+    let (keys, pre, post) = match manifest {
+      SplineManifest::Keys(keys) => (keys, None, None),
+      SplineManifest::Spline { keys, pre, post } => (keys, pre, post)
+    };
+
+    let mut spline = Spline::from_keys(keys.into_iter().map(|key| {
+      let in_handle = key.in_handle.map(|h| Handle::new(h.t, T::from_deserialized(h.value)));
+      let out_handle = key.out_handle.map(|h| Handle::new(h.t, T::from_deserialized(h.value)));
+
+      Key::new_with_handles(key.t, T::from_deserialized(key.value), key.interpolation, in_handle, out_handle)
+    }).collect());
+
+    spline.set_extrapolation(pre, post);
+    spline
+  }
+
+  /// Turn a spline into a `SplineManifest`, converting the keys’ values to their serialized form.
+  ///
+  /// A plain list of keys is used if the spline has no extrapolation.
+  pub fn to_manifest(&self) -> SplineManifest<T::Serialized> where T: SplineSerializerAdapter {
+    let keys = self.keys.iter().map(|key| {
+      let in_handle = key.in_handle.as_ref().map(|h| Handle::new(h.t, h.value.to_serialized()));
+      let out_handle = key.out_handle.as_ref().map(|h| Handle::new(h.t, h.value.to_serialized()));
+
+      Key::new_with_handles(key.t, key.value.to_serialized(), key.interpolation, in_handle, out_handle)
+    }).collect();
+
+    if self.pre.is_none() && self.post.is_none() {
+      SplineManifest::Keys(keys)
+    } else {
+      SplineManifest::Spline {
+        keys: keys,
+        pre: self.pre,
+        post: self.post
+      }
+    }
+  }
+}
+
 impl<T> Load for Spline<T> where T: 'static + SplineDeserializerAdapter {
   fn load<P>(path: P, _: &mut Store) -> Result<LoadResult<Self>, LoadError> where P: AsRef<Path> {
     let path = path.as_ref();
 
-    let file = File::open(path).map_err(|_| LoadError::FileNotFound(path.to_path_buf()))?;
-    let keys: Vec<Key<T::Deserialized>> = from_reader(file).map_err(|e| LoadError::ParseFailed(format!("{:?}", e)))?;
+    let file = File::open(path).map_err(|_| LoadError::FileNotFound(path.to_path_buf()))?;
+    let manifest: SplineManifest<T::Deserialized> = from_reader(file).map_err(|e| LoadError::ParseFailed(format!("{:?}", e)))?;
+
+    Ok(Spline::from_manifest(manifest).into())
+  }
+}
+
+impl<T> Save for Spline<T> where T: SplineSerializerAdapter {
+  fn save<P>(&self, path: P) -> Result<(), SaveError> where P: AsRef<Path> {
+    let path = path.as_ref();
+
+    let file = File::create(path).map_err(|_| SaveError::CannotCreate(path.to_path_buf()))?;
+    to_writer_pretty(file, &self.to_manifest()).map_err(|e| SaveError::SerializationFailed(format!("{:?}", e)))
+  }
+}
+
+/// Spline as written on disk.
+///
+/// A spline is either a plain list of keys or an object with its keys and extrapolation modes:
+///
+/// ```json
+/// {
+///   "keys": [ … ],
+///   "pre": "constant",
+///   "post": "repeat"
+/// }
+/// ```
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum SplineManifest<T> {
+  /// Plain list of keys.
+  Keys(Vec<Key<T>>),
+  /// Keys with extrapolation modes.
+  Spline {
+    keys: Vec<Key<T>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pre: Option<Extrapolation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    post: Option<Extrapolation>
+  }
+}
+
+/// Spline deserializer adapter used to deserialize splines which keys’ values types don’t directly
+/// implement deserialization.
+pub trait SplineDeserializerAdapter {
+  type Deserialized: DeserializeOwned;
+
+  fn from_deserialized(de: Self::Deserialized) -> Self;
+}
+
+impl SplineDeserializerAdapter for f32 {
+  type Deserialized = Self;
+
+  fn from_deserialized(de: Self::Deserialized) -> Self {
+    de
+  }
+}
+
+impl SplineDeserializerAdapter for bool {
+  type Deserialized = Self;
+
+  fn from_deserialized(de: Self::Deserialized) -> Self {
+    de
+  }
+}
+
+impl SplineDeserializerAdapter for i32 {
+  type Deserialized = Self;
 
-    Ok(Spline::from_keys(keys.into_iter().map(|key|
-      Key::new(key.t, T::from_deserialized(key.value), key.interpolation)
-    ).collect()).into())
+  fn from_deserialized(de: Self::Deserialized) -> Self {
+    de
   }
 }
 
-/// Spline deserializer adapter used to deserialize splines which keys’ values types don’t directly
-/// implement deserialization.
-pub trait SplineDeserializerAdapter {
-  type Deserialized: DeserializeOwned;
+impl SplineDeserializerAdapter for u32 {
+  type Deserialized = Self;
 
-  fn from_deserialized(de: Self::Deserialized) -> Self;
+  fn from_deserialized(de: Self::Deserialized) -> Self {
+    de
+  }
 }
 
-impl SplineDeserializerAdapter for f32 {
+impl SplineDeserializerAdapter for String {
   type Deserialized = Self;
 
   fn from_deserialized(de: Self::Deserialized) -> Self {
@@ -282,6 +1081,94 @@ impl SplineDeserializerAdapter for Scale {
   }
 }
 
+/// Spline serializer adapter used to serialize splines which keys’ values types don’t directly
+/// implement serialization. It’s the symmetric of `SplineDeserializerAdapter`.
+pub trait SplineSerializerAdapter {
+  type Serialized: Serialize;
+
+  fn to_serialized(&self) -> Self::Serialized;
+}
+
+impl SplineSerializerAdapter for f32 {
+  type Serialized = Self;
+
+  fn to_serialized(&self) -> Self::Serialized {
+    *self
+  }
+}
+
+impl SplineSerializerAdapter for bool {
+  type Serialized = Self;
+
+  fn to_serialized(&self) -> Self::Serialized {
+    *self
+  }
+}
+
+impl SplineSerializerAdapter for i32 {
+  type Serialized = Self;
+
+  fn to_serialized(&self) -> Self::Serialized {
+    *self
+  }
+}
+
+impl SplineSerializerAdapter for u32 {
+  type Serialized = Self;
+
+  fn to_serialized(&self) -> Self::Serialized {
+    *self
+  }
+}
+
+impl SplineSerializerAdapter for String {
+  type Serialized = Self;
+
+  fn to_serialized(&self) -> Self::Serialized {
+    self.clone()
+  }
+}
+
+impl<T> SplineSerializerAdapter for V2<T> where T: BaseFloat + Serialize {
+  type Serialized = [T; 2];
+
+  fn to_serialized(&self) -> Self::Serialized {
+    (*self).into()
+  }
+}
+
+impl<T> SplineSerializerAdapter for V3<T> where T: BaseFloat + Serialize {
+  type Serialized = [T; 3];
+
+  fn to_serialized(&self) -> Self::Serialized {
+    (*self).into()
+  }
+}
+
+impl<T> SplineSerializerAdapter for V4<T> where T: BaseFloat + Serialize {
+  type Serialized = [T; 4];
+
+  fn to_serialized(&self) -> Self::Serialized {
+    (*self).into()
+  }
+}
+
+impl<T> SplineSerializerAdapter for Quat<T> where T: BaseFloat + Serialize {
+  type Serialized = [T; 4];
+
+  fn to_serialized(&self) -> Self::Serialized {
+    (*self).into()
+  }
+}
+
+impl SplineSerializerAdapter for Scale {
+  type Serialized = [f32; 3];
+
+  fn to_serialized(&self) -> Self::Serialized {
+    (*self).into()
+  }
+}
+
 /// Iterator over spline keys.
 pub struct SplineIterator<'a, T> where T: 'a {
   anim_param: &'a Spline<T>,
@@ -316,15 +1203,56 @@ impl<'a, T> IntoIterator for &'a Spline<T> {
 
 /// Keys that can be interpolated in between. Implementing this trait is required to perform
 /// sampling on splines.
-pub trait Interpolate: Copy {
+///
+/// The arithmetic operators are required to compute derivatives.
+pub trait Interpolate: Copy + Add<Self, Output = Self> + Sub<Self, Output = Self> + Mul<Time, Output = Self> + Div<Time, Output = Self> {
   /// Linear interpolation.
   fn lerp(a: Self, b: Self, t: Time) -> Self;
+  /// Derivative of `Self::lerp` with respect to `t`.
+  ///
+  /// Default to `b - a`.
+  fn lerp_derivative(a: Self, b: Self, _: Time) -> Self {
+    b - a
+  }
+  /// Second derivative of `Self::lerp` with respect to `t`.
+  ///
+  /// Default to zero.
+  fn lerp_second_derivative(a: Self, _: Self, _: Time) -> Self {
+    a - a
+  }
   /// Cubic hermite interpolation.
   ///
   /// Default to `Self::lerp`.
   fn cubic_hermite(_: (Self, Time), a: (Self, Time), b: (Self, Time), _: (Self, Time), t: Time) -> Self {
     Self::lerp(a.0, b.0, t)
   }
+  /// Derivative of `Self::cubic_hermite` with respect to `t`.
+  ///
+  /// Default to `Self::lerp_derivative`.
+  fn cubic_hermite_derivative(_: (Self, Time), a: (Self, Time), b: (Self, Time), _: (Self, Time), t: Time) -> Self {
+    Self::lerp_derivative(a.0, b.0, t)
+  }
+  /// Second derivative of `Self::cubic_hermite` with respect to `t`.
+  ///
+  /// Default to `Self::lerp_second_derivative`.
+  fn cubic_hermite_second_derivative(_: (Self, Time), a: (Self, Time), b: (Self, Time), _: (Self, Time), t: Time) -> Self {
+    Self::lerp_second_derivative(a.0, b.0, t)
+  }
+  /// Tangent at a key for monotone cubic interpolation, given the slopes (in value per unit of
+  /// time) and durations of the segments before and after the key.
+  ///
+  /// Default to the weighted mean of the slopes, which doesn’t prevent overshooting.
+  fn monotone_tangent(d0: Self, dt0: Time, d1: Self, dt1: Time) -> Self {
+    (d0 * dt1 + d1 * dt0) / (dt0 + dt1)
+  }
+  /// Project a value computed by combining values component-wise – as `Interpolation::MonotoneCubic`
+  /// and `Interpolation::KochanekBartels` do – back onto the values the type can take, along with
+  /// its first and second derivatives with respect to time.
+  ///
+  /// Default to the identity.
+  fn project(value: Self, derivative: Self, second_derivative: Self) -> (Self, Self, Self) {
+    (value, derivative, second_derivative)
+  }
 }
 
 impl Interpolate for f32 {
@@ -335,6 +1263,18 @@ impl Interpolate for f32 {
   fn cubic_hermite(x: (Self, Time), a: (Self, Time), b: (Self, Time), y: (Self, Time), t: Time) -> Self {
     cubic_hermite(x, a, b, y, t)
   }
+
+  fn cubic_hermite_derivative(x: (Self, Time), a: (Self, Time), b: (Self, Time), y: (Self, Time), t: Time) -> Self {
+    cubic_hermite_derivative(x, a, b, y, t)
+  }
+
+  fn cubic_hermite_second_derivative(x: (Self, Time), a: (Self, Time), b: (Self, Time), y: (Self, Time), t: Time) -> Self {
+    cubic_hermite_second_derivative(x, a, b, y, t)
+  }
+
+  fn monotone_tangent(d0: Self, dt0: Time, d1: Self, dt1: Time) -> Self {
+    monotone_tangent(d0, dt0, d1, dt1)
+  }
 }
 
 impl Interpolate for V2<f32> {
@@ -345,6 +1285,18 @@ impl Interpolate for V2<f32> {
   fn cubic_hermite(x: (Self, Time), a: (Self, Time), b: (Self, Time), y: (Self, Time), t: Time) -> Self {
     cubic_hermite(x, a, b, y, t)
   }
+
+  fn cubic_hermite_derivative(x: (Self, Time), a: (Self, Time), b: (Self, Time), y: (Self, Time), t: Time) -> Self {
+    cubic_hermite_derivative(x, a, b, y, t)
+  }
+
+  fn cubic_hermite_second_derivative(x: (Self, Time), a: (Self, Time), b: (Self, Time), y: (Self, Time), t: Time) -> Self {
+    cubic_hermite_second_derivative(x, a, b, y, t)
+  }
+
+  fn monotone_tangent(d0: Self, dt0: Time, d1: Self, dt1: Time) -> Self {
+    V2::new(monotone_tangent(d0.x, dt0, d1.x, dt1), monotone_tangent(d0.y, dt0, d1.y, dt1))
+  }
 }
 
 impl Interpolate for V3<f32> {
@@ -355,6 +1307,20 @@ impl Interpolate for V3<f32> {
   fn cubic_hermite(x: (Self, Time), a: (Self, Time), b: (Self, Time), y: (Self, Time), t: Time) -> Self {
     cubic_hermite(x, a, b, y, t)
   }
+
+  fn cubic_hermite_derivative(x: (Self, Time), a: (Self, Time), b: (Self, Time), y: (Self, Time), t: Time) -> Self {
+    cubic_hermite_derivative(x, a, b, y, t)
+  }
+
+  fn cubic_hermite_second_derivative(x: (Self, Time), a: (Self, Time), b: (Self, Time), y: (Self, Time), t: Time) -> Self {
+    cubic_hermite_second_derivative(x, a, b, y, t)
+  }
+
+  fn monotone_tangent(d0: Self, dt0: Time, d1: Self, dt1: Time) -> Self {
+    V3::new(monotone_tangent(d0.x, dt0, d1.x, dt1),
+            monotone_tangent(d0.y, dt0, d1.y, dt1),
+            monotone_tangent(d0.z, dt0, d1.z, dt1))
+  }
 }
 
 impl Interpolate for V4<f32> {
@@ -365,12 +1331,258 @@ impl Interpolate for V4<f32> {
   fn cubic_hermite(x: (Self, Time), a: (Self, Time), b: (Self, Time), y: (Self, Time), t: Time) -> Self {
     cubic_hermite(x, a, b, y, t)
   }
+
+  fn cubic_hermite_derivative(x: (Self, Time), a: (Self, Time), b: (Self, Time), y: (Self, Time), t: Time) -> Self {
+    cubic_hermite_derivative(x, a, b, y, t)
+  }
+
+  fn cubic_hermite_second_derivative(x: (Self, Time), a: (Self, Time), b: (Self, Time), y: (Self, Time), t: Time) -> Self {
+    cubic_hermite_second_derivative(x, a, b, y, t)
+  }
+
+  fn monotone_tangent(d0: Self, dt0: Time, d1: Self, dt1: Time) -> Self {
+    V4::new(monotone_tangent(d0.x, dt0, d1.x, dt1),
+            monotone_tangent(d0.y, dt0, d1.y, dt1),
+            monotone_tangent(d0.z, dt0, d1.z, dt1),
+            monotone_tangent(d0.w, dt0, d1.w, dt1))
+  }
 }
 
+/// Quaternions are expected to be unit quaternions.
+///
+/// `Self::lerp` performs a spherical linear interpolation along the shortest path and
+/// `Self::cubic_hermite` performs a spherical cubic interpolation (SQUAD), so that rotations happen
+/// at constant angular speed and stay smooth across keys.
 impl Interpolate for Quat<f32> {
   fn lerp(a: Self, b: Self, t: Time) -> Self {
-    a.nlerp(b, t)
+    slerp(a, quat_same_hemisphere(a, b), t)
+  }
+
+  fn lerp_derivative(a: Self, b: Self, t: Time) -> Self {
+    // d/dt (a * (a⁻¹ * b)^t) = (a * (a⁻¹ * b)^t) * log(a⁻¹ * b)
+    let b = quat_same_hemisphere(a, b);
+
+    slerp(a, b, t) * quat_log(a.conjugate() * b)
+  }
+
+  fn lerp_second_derivative(a: Self, b: Self, t: Time) -> Self {
+    let b = quat_same_hemisphere(a, b);
+    let l = quat_log(a.conjugate() * b);
+
+    slerp(a, b, t) * l * l
+  }
+
+  fn cubic_hermite(x: (Self, Time), a: (Self, Time), b: (Self, Time), y: (Self, Time), t: Time) -> Self {
+    squad(x.0, a.0, b.0, y.0, t)
+  }
+
+  fn cubic_hermite_derivative(x: (Self, Time), a: (Self, Time), b: (Self, Time), y: (Self, Time), t: Time) -> Self {
+    squad_jet(x.0, a.0, b.0, y.0, t).1
+  }
+
+  fn cubic_hermite_second_derivative(x: (Self, Time), a: (Self, Time), b: (Self, Time), y: (Self, Time), t: Time) -> Self {
+    squad_jet(x.0, a.0, b.0, y.0, t).2
+  }
+
+  /// Normalize the quaternion, so that it’s a rotation again; derivatives are those of `q / |q|`.
+  /// Components are not flipped to the same hemisphere beforehand, so consecutive keys should
+  /// already be.
+  fn project(q: Self, dq: Self, ddq: Self) -> (Self, Self, Self) {
+    let n = q.magnitude();
+    let dn = q.dot(dq) / n;
+    let ddn = (dq.dot(dq) + q.dot(ddq)) / n - dn * dn / n;
+    let n2 = n * n;
+
+    (q / n,
+     dq / n - q * (dn / n2),
+     ddq / n - dq * (2. * dn / n2) - q * (ddn / n2) + q * (2. * dn * dn / (n2 * n)))
+  }
+}
+
+// Flip a quaternion so that it lies in the same hemisphere as a reference one. Both represent the
+// same rotation, but interpolating with the flipped one takes the shortest path.
+fn quat_same_hemisphere(reference: Quat<f32>, q: Quat<f32>) -> Quat<f32> {
+  if reference.dot(q) < 0. { -q } else { q }
+}
+
+/// Spherical linear interpolation between two unit quaternions, without any hemisphere correction.
+pub fn slerp(a: Quat<f32>, b: Quat<f32>, t: Time) -> Quat<f32> {
+  let cos_theta = a.dot(b).max(-1.).min(1.);
+
+  // fall back to nlerp if the quaternions are too close to each other, as sin(θ) goes to zero
+  if cos_theta > 0.9995 {
+    return a.nlerp(b, t);
+  }
+
+  let theta = cos_theta.acos();
+  let sin_theta = theta.sin();
+
+  // opposite quaternions are joined by any great circle; go through a perpendicular quaternion
+  if sin_theta.abs() < 1e-6 {
+    let phi = t * consts::PI;
+    return a * phi.cos() + quat_perpendicular(a) * phi.sin();
+  }
+
+  a * (((1. - t) * theta).sin() / sin_theta) + b * ((t * theta).sin() / sin_theta)
+}
+
+// Quaternion perpendicular to another one, as 4D vectors. The mapping is linear.
+fn quat_perpendicular(q: Quat<f32>) -> Quat<f32> {
+  Quat::new(-q.v.x, q.s, -q.v.z, q.v.y)
+}
+
+// Logarithm of a unit quaternion.
+fn quat_log(q: Quat<f32>) -> Quat<f32> {
+  let sin_theta = q.v.magnitude();
+
+  if sin_theta < 1e-6 {
+    Quat::from_sv(0., q.v)
+  } else {
+    let theta = f32::atan2(sin_theta, q.s);
+    Quat::from_sv(0., q.v * (theta / sin_theta))
+  }
+}
+
+// Exponential of a pure quaternion.
+fn quat_exp(q: Quat<f32>) -> Quat<f32> {
+  let theta = q.v.magnitude();
+
+  if theta < 1e-6 {
+    Quat::from_sv(1., q.v).normalize()
+  } else {
+    Quat::from_sv(theta.cos(), q.v * (theta.sin() / theta))
+  }
+}
+
+// SQUAD inner control point of q, given its neighbours.
+fn squad_control(prev: Quat<f32>, q: Quat<f32>, next: Quat<f32>) -> Quat<f32> {
+  let q_inv = q.conjugate();
+  let l = (quat_log(q_inv * next) + quat_log(q_inv * prev)) * -0.25;
+
+  q * quat_exp(l)
+}
+
+// Spherical cubic interpolation (SQUAD) between q1 and q2, q0 and q3 being their neighbours.
+fn squad(q0: Quat<f32>, q1: Quat<f32>, q2: Quat<f32>, q3: Quat<f32>, t: Time) -> Quat<f32> {
+  // bring all the quaternions in the same hemisphere so that we always take the shortest path
+  let q0 = quat_same_hemisphere(q1, q0);
+  let q2 = quat_same_hemisphere(q1, q2);
+  let q3 = quat_same_hemisphere(q2, q3);
+  let s1 = squad_control(q0, q1, q2);
+  let s2 = squad_control(q1, q2, q3);
+
+  slerp(slerp(q1, q2, t), slerp(s1, s2, t), 2. * t * (1. - t))
+}
+
+// Scalar along with its first and second derivatives, used to differentiate SQUAD analytically.
+#[derive(Clone, Copy)]
+struct Jet {
+  v: f32,
+  d: f32,
+  dd: f32
+}
+
+impl Jet {
+  fn new(v: f32, d: f32, dd: f32) -> Self {
+    Jet {
+      v: v,
+      d: d,
+      dd: dd
+    }
+  }
+
+  fn one_minus(self) -> Self {
+    Jet::new(1. - self.v, -self.d, -self.dd)
+  }
+
+  fn mul(self, rhs: Self) -> Self {
+    Jet::new(self.v * rhs.v,
+             self.d * rhs.v + self.v * rhs.d,
+             self.dd * rhs.v + 2. * self.d * rhs.d + self.v * rhs.dd)
+  }
+
+  fn div(self, rhs: Self) -> Self {
+    let v = self.v / rhs.v;
+    let d = (self.d - v * rhs.d) / rhs.v;
+    let dd = (self.dd - 2. * d * rhs.d - v * rhs.dd) / rhs.v;
+
+    Jet::new(v, d, dd)
+  }
+
+  fn sin(self) -> Self {
+    let (s, c) = (self.v.sin(), self.v.cos());
+    Jet::new(s, c * self.d, c * self.dd - s * self.d * self.d)
+  }
+
+  fn cos(self) -> Self {
+    let (s, c) = (self.v.sin(), self.v.cos());
+    Jet::new(c, -s * self.d, -s * self.dd - c * self.d * self.d)
+  }
+}
+
+// Quaternion along with its first and second derivatives.
+type QuatJet = (Quat<f32>, Quat<f32>, Quat<f32>);
+
+fn quat_constant_jet(q: Quat<f32>) -> QuatJet {
+  let zero = q * 0.;
+  (q, zero, zero)
+}
+
+fn quat_dot_jet(a: QuatJet, b: QuatJet) -> Jet {
+  Jet::new(a.0.dot(b.0),
+           a.1.dot(b.0) + a.0.dot(b.1),
+           a.2.dot(b.0) + 2. * a.1.dot(b.1) + a.0.dot(b.2))
+}
+
+// Derivatives of wa * a + wb * b.
+fn quat_combine_jet(wa: Jet, a: QuatJet, wb: Jet, b: QuatJet) -> QuatJet {
+  (a.0 * wa.v + b.0 * wb.v,
+   a.0 * wa.d + a.1 * wa.v + b.0 * wb.d + b.1 * wb.v,
+   a.0 * wa.dd + a.1 * (2. * wa.d) + a.2 * wa.v + b.0 * wb.dd + b.1 * (2. * wb.d) + b.2 * wb.v)
+}
+
+// Derivatives of slerp(a, b, t) when a, b and t all vary. The branches are the same as slerp’s.
+fn slerp_jet(a: QuatJet, b: QuatJet, t: Jet) -> QuatJet {
+  let c = quat_dot_jet(a, b);
+  let cos_theta = c.v.max(-1.).min(1.);
+
+  if cos_theta > 0.9995 {
+    // the normalization of nlerp is negligible for such close quaternions
+    return quat_combine_jet(t.one_minus(), a, t, b);
+  }
+
+  let theta = cos_theta.acos();
+  let sin_theta = theta.sin();
+
+  if sin_theta.abs() < 1e-6 {
+    let p = (quat_perpendicular(a.0), quat_perpendicular(a.1), quat_perpendicular(a.2));
+    let phi = t.mul(Jet::new(consts::PI, 0., 0.));
+
+    return quat_combine_jet(phi.cos(), a, phi.sin(), p);
   }
+
+  // cos(θ) = c, differentiated twice
+  let d_theta = -c.d / sin_theta;
+  let theta = Jet::new(theta, d_theta, -(c.dd + cos_theta * d_theta * d_theta) / sin_theta);
+  let sin_theta = theta.sin();
+  let wa = t.one_minus().mul(theta).sin().div(sin_theta);
+  let wb = t.mul(theta).sin().div(sin_theta);
+
+  quat_combine_jet(wa, a, wb, b)
+}
+
+// SQUAD along with its first and second derivatives with respect to t.
+fn squad_jet(q0: Quat<f32>, q1: Quat<f32>, q2: Quat<f32>, q3: Quat<f32>, t: Time) -> QuatJet {
+  let q0 = quat_same_hemisphere(q1, q0);
+  let q2 = quat_same_hemisphere(q1, q2);
+  let q3 = quat_same_hemisphere(q2, q3);
+  let s1 = squad_control(q0, q1, q2);
+  let s2 = squad_control(q1, q2, q3);
+  let t_jet = Jet::new(t, 1., 0.);
+  let p = slerp_jet(quat_constant_jet(q1), quat_constant_jet(q2), t_jet);
+  let s = slerp_jet(quat_constant_jet(s1), quat_constant_jet(s2), t_jet);
+
+  slerp_jet(p, s, Jet::new(2. * t * (1. - t), 2. - 4. * t, -4.))
 }
 
 impl Interpolate for Scale {
@@ -381,6 +1593,12 @@ impl Interpolate for Scale {
 
     Scale::new(r.x, r.y, r.z)
   }
+
+  fn monotone_tangent(d0: Self, dt0: Time, d1: Self, dt1: Time) -> Self {
+    Scale::new(monotone_tangent(d0.x, dt0, d1.x, dt1),
+               monotone_tangent(d0.y, dt0, d1.y, dt1),
+               monotone_tangent(d0.z, dt0, d1.z, dt1))
+  }
 }
 
 // Default implementation of Interpolate::cubic_hermit.
@@ -399,40 +1617,255 @@ pub fn cubic_hermite<T>(x: (T, Time), a: (T, Time), b: (T, Time), y: (T, Time),
   a.0 * (two_t3 - three_t2 + 1.) + m0 * (t3 - 2. * t2 + t) + b.0 * (-two_t3 + three_t2) + m1 * (t3 - t2)
 }
 
+// Default implementation of Interpolate::cubic_hermite_derivative.
+pub fn cubic_hermite_derivative<T>(x: (T, Time), a: (T, Time), b: (T, Time), y: (T, Time), t: Time) -> T
+    where T: Copy + Add<Output = T> + Sub<Output = T> + Mul<Time, Output = T> + Div<Time, Output = T> {
+  // time stuff
+  let t2 = t * t;
+  let six_t2 = 6. * t2;
+  let six_t = 6. * t;
+
+  // tangents
+  let m0 = (b.0 - x.0) / (b.1 - x.1);
+  let m1 = (y.0 - a.0) / (y.1 - a.1);
+
+  a.0 * (six_t2 - six_t) + m0 * (3. * t2 - 4. * t + 1.) + b.0 * (six_t - six_t2) + m1 * (3. * t2 - 2. * t)
+}
+
+// Default implementation of Interpolate::cubic_hermite_second_derivative.
+pub fn cubic_hermite_second_derivative<T>(x: (T, Time), a: (T, Time), b: (T, Time), y: (T, Time), t: Time) -> T
+    where T: Copy + Add<Output = T> + Sub<Output = T> + Mul<Time, Output = T> + Div<Time, Output = T> {
+  let twelve_t = 12. * t;
+
+  // tangents
+  let m0 = (b.0 - x.0) / (b.1 - x.1);
+  let m1 = (y.0 - a.0) / (y.1 - a.1);
+
+  a.0 * (twelve_t - 6.) + m0 * (6. * t - 4.) + b.0 * (6. - twelve_t) + m1 * (6. * t - 2.)
+}
+
+// Cubic Hermite interpolation (or one of its derivatives) with explicit tangents, expressed for a
+// normalized time. dt is the duration of the segment, used to get derivatives with respect to time.
+fn hermite_order<T>(a: T, m0: T, b: T, m1: T, t: Time, dt: Time, order: Order) -> T where T: Interpolate {
+  let t2 = t * t;
+  let t3 = t2 * t;
+
+  match order {
+    Order::Value => {
+      a * (2. * t3 - 3. * t2 + 1.) + m0 * (t3 - 2. * t2 + t) + b * (-2. * t3 + 3. * t2) + m1 * (t3 - t2)
+    },
+    Order::First => {
+      (a * (6. * t2 - 6. * t) + m0 * (3. * t2 - 4. * t + 1.) + b * (6. * t - 6. * t2) + m1 * (3. * t2 - 2. * t)) / dt
+    },
+    Order::Second => {
+      (a * (12. * t - 6.) + m0 * (6. * t - 4.) + b * (6. - 12. * t) + m1 * (6. * t - 2.)) / (dt * dt)
+    }
+  }
+}
+
+// Tension, continuity and bias of a key; 0 if it’s not a TCB key.
+fn tcb_parameters(interpolation: &Interpolation) -> (f32, f32, f32) {
+  match *interpolation {
+    Interpolation::KochanekBartels { tension, continuity, bias } => (tension, continuity, bias),
+    _ => (0., 0., 0.)
+  }
+}
+
+// Incoming and outgoing Kochanek-Bartels tangents at a key, given its neighbours.
+//
+// Tangents are expressed for the normalized time of the segment they belong to; they’re adjusted
+// to account for keys not being evenly spaced in time.
+fn tcb_tangents<T>(prev: &Key<T>, key: &Key<T>, next: &Key<T>, tension: f32, continuity: f32, bias: f32) -> (T, T)
+    where T: Interpolate {
+  let d0 = key.value - prev.value;
+  let d1 = next.value - key.value;
+  let dt0 = key.t - prev.t;
+  let dt1 = next.t - key.t;
+  let it = 1. - tension;
+
+  let incoming = d0 * (it * (1. - continuity) * (1. + bias) * 0.5) + d1 * (it * (1. + continuity) * (1. - bias) * 0.5);
+  let outgoing = d0 * (it * (1. + continuity) * (1. + bias) * 0.5) + d1 * (it * (1. - continuity) * (1. - bias) * 0.5);
+
+  (incoming * (2. * dt0 / (dt0 + dt1)), outgoing * (2. * dt1 / (dt0 + dt1)))
+}
+
+// Fritsch-Carlson tangent at a key for scalar values, given the slopes and durations of the segments
+// around it. We use Brodlie’s weighted harmonic mean, which keeps every segment monotonic on its
+// own; the tangent is null at local extrema.
+pub fn monotone_tangent(d0: f32, dt0: Time, d1: f32, dt1: Time) -> f32 {
+  if d0 * d1 <= 0. {
+    0.
+  } else {
+    let w0 = 2. * dt1 + dt0;
+    let w1 = dt1 + 2. * dt0;
+
+    (w0 + w1) / (w0 / d0 + w1 / d1)
+  }
+}
+
+// Order of derivation used when sampling a spline.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Order {
+  Value,
+  First,
+  Second
+}
+
+// Sample Interpolate::lerp (or one of its derivatives) with a remapped time s, given its first and
+// second derivatives with respect to time.
+fn lerp_order<T>(a: T, b: T, s: Time, ds: Time, dds: Time, order: Order) -> T where T: Interpolate {
+  match order {
+    Order::Value => T::lerp(a, b, s),
+    Order::First => T::lerp_derivative(a, b, s) * ds,
+    Order::Second => T::lerp_second_derivative(a, b, s) * (ds * ds) + T::lerp_derivative(a, b, s) * dds
+  }
+}
+
+// Get the Bézier handles of the segment defined by two control points. Missing handles are set to
+// their control point and handles’ times are clamped to the segment.
+fn bezier_handles<T>(cp0: &Key<T>, cp1: &Key<T>) -> (Handle<T>, Handle<T>) where T: Copy {
+  let clamp = |t: Time| t.max(cp0.t).min(cp1.t);
+  let h0 = cp0.out_handle.map_or(Handle::new(cp0.t, cp0.value), |h| Handle::new(clamp(h.t), h.value));
+  let h1 = cp1.in_handle.map_or(Handle::new(cp1.t, cp1.value), |h| Handle::new(clamp(h.t), h.value));
+
+  (h0, h1)
+}
+
+// Cubic Bézier evaluation (De Casteljau), only relying on Interpolate::lerp.
+pub fn bezier<T>(p0: T, p1: T, p2: T, p3: T, u: Time) -> T where T: Interpolate {
+  let a = T::lerp(p0, p1, u);
+  let b = T::lerp(p1, p2, u);
+  let c = T::lerp(p2, p3, u);
+  let d = T::lerp(a, b, u);
+  let e = T::lerp(b, c, u);
+
+  T::lerp(d, e, u)
+}
+
+// First and second derivatives of a cubic Bézier with respect to its parameter.
+fn bezier_derivatives<T>(p0: T, p1: T, p2: T, p3: T, u: Time) -> (T, T) where T: Interpolate {
+  let a = T::lerp(p0, p1, u);
+  let b = T::lerp(p1, p2, u);
+  let c = T::lerp(p2, p3, u);
+  let d = T::lerp(a, b, u);
+  let e = T::lerp(b, c, u);
+
+  ((e - d) * 3., (a - b - b + c) * 6.)
+}
+
+// Scalar cubic Bézier and its first and second derivatives.
+fn bezier_scalar(p0: f32, p1: f32, p2: f32, p3: f32, u: f32) -> (f32, f32, f32) {
+  let iu = 1. - u;
+  let x = iu * iu * iu * p0 + 3. * iu * iu * u * p1 + 3. * iu * u * u * p2 + u * u * u * p3;
+  let dx = 3. * iu * iu * (p1 - p0) + 6. * iu * u * (p2 - p1) + 3. * u * u * (p3 - p2);
+  let ddx = 6. * iu * (p2 - 2. * p1 + p0) + 6. * u * (p3 - 2. * p2 + p1);
+
+  (x, dx, ddx)
+}
+
+// Find the Bézier parameter at which the time curve defined by t0, t1, t2 and t3 reaches t.
+//
+// The time curve is monotonic because handles are clamped to the segment, so we use Newton-Raphson
+// steps and fall back to bisection whenever a step leaves the current bracket.
+fn bezier_solve_time(t0: Time, t1: Time, t2: Time, t3: Time, t: Time) -> Time {
+  let mut lo = 0.;
+  let mut hi = 1.;
+  let mut u = ((t - t0) / (t3 - t0)).max(0.).min(1.);
+
+  for _ in 0..32 {
+    let (x, dx, _) = bezier_scalar(t0, t1, t2, t3, u);
+    let err = x - t;
+
+    if err.abs() < 1e-6 {
+      break;
+    }
+
+    if err > 0. {
+      hi = u;
+    } else {
+      lo = u;
+    }
+
+    let next = u - err / dx;
+
+    u = if dx.abs() > 1e-6 && next > lo && next < hi { next } else { (lo + hi) * 0.5 };
+  }
+
+  u
+}
+
 // Normalize a time ([0;1]) given two control points.
 pub fn normalize_time<T>(t: Time, cp: &Key<T>, cp1: &Key<T>) -> Time {
   (t - cp.t) / (cp1.t - cp.t)
 }
 
+// Check that a time can be used in a spline.
+fn check_time(t: Time) -> Result<(), SplineError> {
+  if t.is_finite() {
+    Ok(())
+  } else {
+    Err(SplineError::InvalidTime(t))
+  }
+}
+
+/// Index of the first key strictly after a given time, the keys being sorted.
+///
+/// It’s also the index at which a key with that time must be inserted to keep the keys sorted,
+/// after any key with the same time.
+pub fn upper_bound<T>(keys: &[Key<T>], t: Time) -> usize {
+  let mut lo = 0;
+  let mut hi = keys.len();
+
+  while lo < hi {
+    let mid = (lo + hi) / 2;
+
+    if keys[mid].t <= t {
+      lo = mid + 1;
+    } else {
+      hi = mid;
+    }
+  }
+
+  lo
+}
+
 // Find the lower control point corresponding to a given time.
+//
+// The keys must be sorted. If several keys share the same time, the last one is picked so that
+// the segment is never empty.
 fn search_lower_cp<T>(cps: &[Key<T>], t: Time) -> Option<usize> {
-  let mut i = 0;
   let len = cps.len();
 
-  if len < 2 {
+  if len < 2 || t < cps[0].t || t >= cps[len - 1].t {
     return None;
   }
 
-  loop {
-    let cp = &cps[i];
-    let cp1 = &cps[i+1];
+  Some(upper_bound(cps, t) - 1)
+}
 
-    if t >= cp1.t {
-      if i >= len - 2 {
-        return None;
-      }
+// Find the lower control point corresponding to a given time, starting from a cursor. The cursor’s
+// segment and the next one are looked up first, then we fall back to a binary search. The cursor
+// is updated with the found control point.
+fn search_lower_cp_from<T>(cps: &[Key<T>], t: Time, cursor: &mut usize) -> Option<usize> {
+  let len = cps.len();
+  let c = *cursor;
 
-      i += 1;
-    } else if t < cp.t {
-      if i == 0 {
-        return None;
-      }
+  if c + 1 < len && cps[c].t <= t {
+    if t < cps[c + 1].t {
+      return Some(c);
+    }
 
-      i -= 1;
-    } else {
-      break; // found
+    if c + 2 < len && t < cps[c + 2].t {
+      *cursor = c + 1;
+      return Some(c + 1);
     }
   }
 
-  Some(i)
+  let i = search_lower_cp(cps, t);
+
+  if let Some(i) = i {
+    *cursor = i;
+  }
+
+  i
 }
@@ -0,0 +1,144 @@
+//! Transform splines.
+//!
+//! A `TransformSpline` animates the position, orientation and scale of an object with three
+//! independent splines, so that each of them can have its own keys. Sampling it yields a `Pose`,
+//! which can be turned into a `Transform`.
+//!
+//! Transform splines are stored as a single resource:
+//!
+//! ```json
+//! {
+//!   "position": [ … ],
+//!   "orientation": [ … ],
+//!   "scale": { "keys": [ … ], "post": "ping_pong" }
+//! }
+//! ```
+//!
+//! Every field follows the same format as spline files and is optional.
+
+use serde_json::{from_reader, to_writer_pretty};
+use std::fs::File;
+use std::path::Path;
+
+use anim::spline::{Interpolate, Spline, SplineDeserializerAdapter, SplineManifest, SplineResourceKey, Time};
+use linear::{M44, Quat, Scale, V3};
+use scene::transform::{Transform, Transformable};
+use sys::resource::{Load, LoadError, LoadResult, Save, SaveError, Store};
+
+/// Position, orientation and scale of an object at a given time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pose {
+  pub position: V3<f32>,
+  pub orientation: Quat<f32>,
+  pub scale: Scale
+}
+
+impl Default for Pose {
+  fn default() -> Self {
+    Pose {
+      position: V3::new(0., 0., 0.),
+      orientation: Quat::new(1., 0., 0., 0.),
+      scale: Scale::default()
+    }
+  }
+}
+
+impl Transformable for Pose {
+  fn transform(&self) -> Transform {
+    (M44::from_translation(self.position) * M44::from(self.orientation) * M44::from(self.scale)).into()
+  }
+}
+
+/// Spline animating a position, an orientation and a scale.
+///
+/// An empty spline leaves its part of the pose untouched (no translation, no rotation or unit
+/// scale). Otherwise, splines are sampled with `Spline::try_clamped_sample`: outside of its keys, a
+/// spline uses its extrapolation modes if any and holds its first or last value otherwise.
+#[derive(Clone, Debug)]
+pub struct TransformSpline {
+  pub position: Spline<V3<f32>>,
+  pub orientation: Spline<Quat<f32>>,
+  pub scale: Spline<Scale>
+}
+
+impl TransformSpline {
+  pub fn new(position: Spline<V3<f32>>, orientation: Spline<Quat<f32>>, scale: Spline<Scale>) -> Self {
+    TransformSpline {
+      position: position,
+      orientation: orientation,
+      scale: scale
+    }
+  }
+
+  /// Sample the pose at a given time.
+  pub fn sample(&self, t: Time) -> Pose {
+    let default = Pose::default();
+
+    Pose {
+      position: sample_or(&self.position, t, default.position),
+      orientation: sample_or(&self.orientation, t, default.orientation),
+      scale: sample_or(&self.scale, t, default.scale)
+    }
+  }
+
+  /// Sample the transform at a given time.
+  pub fn sample_transform(&self, t: Time) -> Transform {
+    self.sample(t).transform()
+  }
+}
+
+// Sample a spline with clamping, falling back to a default value if it has no key.
+fn sample_or<T>(spline: &Spline<T>, t: Time, default: T) -> T where T: Interpolate {
+  spline.try_clamped_sample(t).unwrap_or(default)
+}
+
+#[derive(Deserialize, Serialize)]
+struct TransformSplineManifest<P, O, S> {
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  position: Option<SplineManifest<P>>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  orientation: Option<SplineManifest<O>>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  scale: Option<SplineManifest<S>>
+}
+
+// Turn an optional manifest into a spline, an absent manifest yielding an empty spline.
+fn from_optional_manifest<T>(manifest: Option<SplineManifest<T::Deserialized>>) -> Spline<T> where T: SplineDeserializerAdapter {
+  manifest.map(Spline::from_manifest).unwrap_or_else(|| Spline::from_keys(Vec::new()))
+}
+
+/// Key of a transform spline in the store.
+pub type TransformSplineKey = SplineResourceKey<TransformSpline>;
+
+impl Load for TransformSpline {
+  fn load<P>(path: P, _: &mut Store) -> Result<LoadResult<Self>, LoadError> where P: AsRef<Path> {
+    let path = path.as_ref();
+
+    let file = File::open(path).map_err(|_| LoadError::FileNotFound(path.to_path_buf()))?;
+    let manifest: TransformSplineManifest<[f32; 3], [f32; 4], [f32; 3]> = from_reader(file).map_err(|e| LoadError::ParseFailed(format!("{:?}", e)))?;
+
+    let spline = TransformSpline {
+      position: from_optional_manifest(manifest.position),
+      orientation: from_optional_manifest(manifest.orientation),
+      scale: from_optional_manifest(manifest.scale)
+    };
+
+    Ok(spline.into())
+  }
+}
+
+impl Save for TransformSpline {
+  fn save<P>(&self, path: P) -> Result<(), SaveError> where P: AsRef<Path> {
+    let path = path.as_ref();
+
+    // empty splines are left out so that they’re loaded back as such
+    let manifest = TransformSplineManifest {
+      position: if self.position.keys().is_empty() { None } else { Some(self.position.to_manifest()) },
+      orientation: if self.orientation.keys().is_empty() { None } else { Some(self.orientation.to_manifest()) },
+      scale: if self.scale.keys().is_empty() { None } else { Some(self.scale.to_manifest()) }
+    };
+
+    let file = File::create(path).map_err(|_| SaveError::CannotCreate(path.to_path_buf()))?;
+    to_writer_pretty(file, &manifest).map_err(|e| SaveError::SerializationFailed(format!("{:?}", e)))
+  }
+}
@@ -1,6 +1,6 @@
 pub use num_traits::One;
 use std::default::Default;
-use std::ops::Mul;
+use std::ops::{Add, Div, Mul, Sub};
 
 use linear::M44;
 use scene::transform::{Transform, Transformable};
@@ -86,6 +86,54 @@ impl Mul for Scale {
   }
 }
 
+impl Add for Scale {
+  type Output = Scale;
+
+  fn add(self, rhs: Self) -> Self::Output {
+    Scale {
+      x: self.x + rhs.x,
+      y: self.y + rhs.y,
+      z: self.z + rhs.z
+    }
+  }
+}
+
+impl Sub for Scale {
+  type Output = Scale;
+
+  fn sub(self, rhs: Self) -> Self::Output {
+    Scale {
+      x: self.x - rhs.x,
+      y: self.y - rhs.y,
+      z: self.z - rhs.z
+    }
+  }
+}
+
+impl Mul<f32> for Scale {
+  type Output = Scale;
+
+  fn mul(self, rhs: f32) -> Self::Output {
+    Scale {
+      x: self.x * rhs,
+      y: self.y * rhs,
+      z: self.z * rhs
+    }
+  }
+}
+
+impl Div<f32> for Scale {
+  type Output = Scale;
+
+  fn div(self, rhs: f32) -> Self::Output {
+    Scale {
+      x: self.x / rhs,
+      y: self.y / rhs,
+      z: self.z / rhs
+    }
+  }
+}
+
 impl One for Scale {
   fn one() -> Self {
     Scale::new(1., 1., 1.)
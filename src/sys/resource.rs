@@ -6,7 +6,7 @@
 //! the loading code and ask the resource system to cache it for you.
 //!
 //! This great flexibility is exposed in the public interface so that the cache can be augmented
-//! with user-provided objects. You might be interested in implementing `Load`, `CacheKey` – from
+//! with user-provided objects. You might be interested in implementing `Load`, `Save`, `CacheKey` – from
 //! the [any-cache](https://crates.io/crates/any-cache) crate — as well as providing a type wrapper
 //! over the key to access to your resource.
 //!
@@ -45,6 +45,16 @@ pub trait Load: 'static + Sized {
   fn load<P>(path: P, cache: &mut Store) -> Result<LoadResult<Self>, LoadError> where P: AsRef<Path>;
 }
 
+/// Savable object to disk.
+///
+/// This is the symmetric of `Load`: a resource that can be saved can be written back to the file it
+/// was loaded from. Use `Store::save` to save a resource held by a `Store`, which will then
+/// hot-reload it as for any other file change.
+pub trait Save {
+  /// Save a resource.
+  fn save<P>(&self, path: P) -> Result<(), SaveError> where P: AsRef<Path>;
+}
+
 /// Result of a resource loading. This type enables you to register a resource for reloading events
 /// of others (dependencies). If you don’t need to run specific code on a dependency reloading, use
 /// the `.into()` function to lift your return value to `LoadResult<_>`.
@@ -81,6 +91,17 @@ pub enum LoadError {
   ConversionFailed(String)
 }
 
+/// Error that might occur while saving a resource.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SaveError {
+  /// The resource is not in the store; the path it would be saved to is given.
+  NotInStore(PathBuf),
+  /// The file couldn’t be created.
+  CannotCreate(PathBuf),
+  /// The resource wasn’t correctly serialized.
+  SerializationFailed(String)
+}
+
 /// Resources are wrapped in this type.
 pub type Res<T> = Rc<RefCell<T>>;
 
@@ -259,6 +280,22 @@ impl Store {
     }
   }
 
+  /// Save a resource from the store to disk.
+  ///
+  /// The resource is written to the path associated with its key, so it will get reloaded on the
+  /// next synchronization.
+  pub fn save<K>(&self, key: &K) -> Result<(), SaveError> where K: StoreKey, K::Target: Save {
+    let path = self.root.join(K::key_to_path(key));
+
+    match self.cache.get(&RKey(key.clone())) {
+      Some(resource) => {
+        info!("saving {:?}", key);
+        resource.borrow().save(&path)
+      },
+      None => Err(SaveError::NotInStore(path))
+    }
+  }
+
   /// Synchronize the cache by updating the resources that ought to.
   pub fn sync(&mut self) {
     let dirty = self.dirty.clone();
@@ -1,9 +1,30 @@
 extern crate rand;
+extern crate serde_json;
 extern crate spectra;
 
 use rand::{Rng, thread_rng};
-use spectra::linear::Quaternion;
-use spectra::spline::*;
+use spectra::anim::arc_length::ArcLengthTable;
+use spectra::anim::bake::BakedSpline;
+use spectra::anim::blend::{Blend, BlendMode, Layer};
+use spectra::anim::clip::{AnimationClip, AnimationClipKey};
+use spectra::anim::codegen::{CodegenError, spline_to_glsl, spline_to_module};
+use spectra::anim::discrete::{DiscreteSpline, DiscreteSplineKey};
+use spectra::anim::easing::Easing;
+use spectra::anim::edit::{Clip, Cut, CutContext, CutManifest, Fold, FoldParams, Overlap, Played, Timeline,
+                          TimelineIssue, TimelineManifest, Track, TrackManifest};
+use spectra::anim::noise::{Noise, NoiseKind, NoiseModifier};
+use spectra::anim::query::{CrossingDirection, ExtremumKind};
+use spectra::anim::simplify::simplify;
+use spectra::anim::spline::*;
+use spectra::anim::transform::TransformSpline;
+use spectra::linear::{Quaternion, Scale, V2, V3, V4};
+use spectra::sys::resource::{Save, SaveError, Store};
+use std::collections::HashMap;
+use std::f32::consts;
+use std::fmt::Debug;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
 
 #[test]
 fn hold() {
@@ -58,3 +79,1079 @@ fn keys_sorted() {
     t = key.t;
   }
 }
+
+#[test]
+fn bezier() {
+  let spline = Spline::from_keys(vec![
+    Key::new_with_handles(0., 0., Interpolation::Bezier, None, Some(Handle::new(5., 0.))),
+    Key::new_with_handles(10., 10., Interpolation::Bezier, Some(Handle::new(5., 10.)), None),
+    Key::new(20., 0., Interpolation::Linear)
+  ]);
+
+  assert_eq!(spline.sample(0.), Some(0.));
+  assert!((spline.sample(5.).unwrap() - 5.).abs() < 1e-4);
+  assert!(spline.sample(2.).unwrap() < 2.);
+  assert!(spline.sample(8.).unwrap() > 8.);
+  assert!((spline.sample(10.).unwrap() - 10.).abs() < 1e-4);
+
+  // without handles, time and value are weighted alike
+  let no_handle = Spline::from_keys(vec![
+    Key::new(0., 0., Interpolation::Bezier),
+    Key::new(10., 10., Interpolation::Linear)
+  ]);
+
+  for &t in &[1., 2.5, 5., 9.] {
+    assert!((no_handle.sample(t).unwrap() - t).abs() < 1e-3);
+  }
+}
+
+#[test]
+fn derivative() {
+  let spline = Spline::from_keys(vec![
+    Key::new(0., 10., Interpolation::Linear),
+    Key::new(10., 20., Interpolation::Cosine),
+    Key::new(20., 0., Interpolation::Linear)
+  ]);
+
+  assert_eq!(spline.sample_derivative(5.), Some(1.));
+  assert_eq!(spline.sample_second_derivative(5.), Some(0.));
+  assert!((spline.sample_derivative(15.).unwrap() + consts::PI).abs() < 1e-4);
+  assert!(spline.sample_derivative(10.).unwrap().abs() < 1e-4);
+  assert_eq!(spline.sample_derivative(20.), None);
+}
+
+#[test]
+fn quat_slerp() {
+  let h = consts::FRAC_1_SQRT_2;
+  let spline = Spline::from_keys(vec![
+    Key::new(0., Quaternion::new(1., 0., 0., 0.), Interpolation::Linear),
+    Key::new(1., Quaternion::new(0., 0., 0., 1.), Interpolation::Linear),
+    // same rotation as a quarter turn around Z, in the opposite hemisphere
+    Key::new(2., Quaternion::new(-h, 0., 0., -h), Interpolation::Linear)
+  ]);
+
+  let q = spline.sample(0.5).unwrap();
+  assert!((q.s - h).abs() < 1e-4 && (q.v.z - h).abs() < 1e-4);
+
+  let q = spline.sample(1.5).unwrap();
+  assert!((q.s.abs() - (3. * consts::PI / 8.).cos()).abs() < 1e-4);
+}
+
+#[test]
+fn quat_slerp_antipodal() {
+  let a = Quaternion::new(1., 0., 0., 0.);
+  let near = Quaternion::new(-(0.01f32).cos(), 0., 0., (0.01f32).sin());
+
+  for &b in &[-a, near] {
+    for &t in &[0., 0.25, 0.5, 0.75, 1.] {
+      let q = slerp(a, b, t);
+      let norm = (q.s * q.s + q.v.x * q.v.x + q.v.y * q.v.y + q.v.z * q.v.z).sqrt();
+
+      assert!((norm - 1.).abs() < 1e-4);
+    }
+  }
+
+  let q = slerp(a, -a, 1.);
+  assert!((q.s + 1.).abs() < 1e-4);
+}
+
+#[test]
+fn quat_component_wise() {
+  let z = |angle: f32| Quaternion::new((angle * 0.5).cos(), 0., 0., (angle * 0.5).sin());
+  let tcb = Interpolation::KochanekBartels { tension: 0.3, continuity: 0., bias: 0. };
+  let angles = [0., 0.8, 1.2, 2.5];
+
+  for &interpolation in &[tcb, Interpolation::MonotoneCubic] {
+    let spline = Spline::from_keys(angles.iter().enumerate().map(|(i, &a)| Key::new(i as f32, z(a), interpolation)).collect());
+    let e = 1e-2;
+
+    for &t in &[1.1, 1.5, 1.9] {
+      let q = spline.sample(t).unwrap();
+      let norm = (q.s * q.s + q.v.x * q.v.x + q.v.y * q.v.y + q.v.z * q.v.z).sqrt();
+      assert!((norm - 1.).abs() < 1e-5);
+
+      let d = spline.sample_derivative(t).unwrap();
+      let fd = (spline.sample(t + e).unwrap() - spline.sample(t - e).unwrap()) / (2. * e);
+      assert!((d.s - fd.s).abs() < 1e-2 && (d.v.z - fd.v.z).abs() < 1e-2);
+
+      let dd = spline.sample_second_derivative(t).unwrap();
+      let fdd = (spline.sample_derivative(t + e).unwrap() - spline.sample_derivative(t - e).unwrap()) / (2. * e);
+      assert!((dd.s - fdd.s).abs() < 1e-2 && (dd.v.z - fdd.v.z).abs() < 1e-2);
+    }
+  }
+}
+
+#[test]
+fn quat_squad_derivative() {
+  let z = |angle: f32| Quaternion::new((angle * 0.5).cos(), 0., 0., (angle * 0.5).sin());
+  let spline = Spline::from_keys(vec![
+    Key::new(0., z(0.), Interpolation::CatmullRom),
+    Key::new(1., z(0.5), Interpolation::CatmullRom),
+    Key::new(2., z(1.2), Interpolation::CatmullRom),
+    Key::new(3., z(2.), Interpolation::CatmullRom)
+  ]);
+
+  let e = 1e-2;
+
+  for &t in &[1.1, 1.5, 1.9] {
+    let d = spline.sample_derivative(t).unwrap();
+    let fd = (spline.sample(t + e).unwrap() - spline.sample(t - e).unwrap()) / (2. * e);
+    assert!((d.s - fd.s).abs() < 1e-2 && (d.v.z - fd.v.z).abs() < 1e-2);
+
+    let dd = spline.sample_second_derivative(t).unwrap();
+    let fdd = (spline.sample_derivative(t + e).unwrap() - spline.sample_derivative(t - e).unwrap()) / (2. * e);
+    assert!((dd.s - fdd.s).abs() < 1e-2 && (dd.v.z - fdd.v.z).abs() < 1e-2);
+  }
+}
+
+#[test]
+fn extrapolation() {
+  let mut spline = Spline::from_keys(vec![
+    Key::new(0., 0., Interpolation::Linear),
+    Key::new(10., 10., Interpolation::Linear)
+  ]);
+
+  assert_eq!(spline.sample(-5.), None);
+  assert_eq!(spline.sample(15.), None);
+
+  spline.set_extrapolation(Some(Extrapolation::Constant), Some(Extrapolation::Linear));
+  assert_eq!(spline.sample(-5.), Some(0.));
+  assert_eq!(spline.sample(15.), Some(15.));
+
+  spline.set_extrapolation(Some(Extrapolation::Repeat), Some(Extrapolation::PingPong));
+  assert_eq!(spline.sample(-5.), Some(5.));
+  assert_eq!(spline.sample(12.), Some(8.));
+  assert_eq!(spline.sample_derivative(12.), Some(-1.));
+  assert_eq!(spline.sample(22.), Some(2.));
+
+  spline.set_extrapolation(Some(Extrapolation::OffsetRepeat), Some(Extrapolation::OffsetRepeat));
+  assert_eq!(spline.sample(-5.), Some(-5.));
+  assert_eq!(spline.sample(25.), Some(25.));
+}
+
+#[test]
+fn extrapolation_edge_cases() {
+  let mut spline = Spline::from_keys(vec![
+    Key::new(0., 0., Interpolation::CatmullRom),
+    Key::new(1., 1., Interpolation::CatmullRom),
+    Key::new(2., 3., Interpolation::CatmullRom),
+    Key::new(3., 4., Interpolation::CatmullRom)
+  ]);
+  spline.set_extrapolation(Some(Extrapolation::Linear), Some(Extrapolation::Linear));
+
+  assert_eq!(spline.sample(-1.), Some(-1.));
+  assert_eq!(spline.sample(5.), Some(6.));
+  assert_eq!(spline.sample_derivative(5.), Some(1.));
+
+  let mut single_time = Spline::from_keys(vec![
+    Key::new(1., 2., Interpolation::Linear),
+    Key::new(1., 2., Interpolation::Linear)
+  ]);
+
+  for &mode in &[Extrapolation::Linear, Extrapolation::Repeat, Extrapolation::PingPong, Extrapolation::OffsetRepeat] {
+    single_time.set_extrapolation(Some(mode), Some(mode));
+    assert_eq!(single_time.sample(0.), Some(2.));
+    assert_eq!(single_time.sample(3.), Some(2.));
+  }
+
+  let mut single_key = Spline::from_keys(vec![Key::new(1., 2., Interpolation::Linear)]);
+  assert_eq!(single_key.sample(1.), None);
+
+  single_key.set_extrapolation(Some(Extrapolation::Constant), Some(Extrapolation::Repeat));
+  assert_eq!(single_key.sample(0.), Some(2.));
+  assert_eq!(single_key.sample(1.), Some(2.));
+  assert_eq!(single_key.sample(3.), Some(2.));
+  assert_eq!(single_key.sample_derivative(3.), Some(0.));
+
+  single_key.set_extrapolation(None, Some(Extrapolation::Linear));
+  assert_eq!(single_key.sample(0.), None);
+  assert_eq!(single_key.sample(3.), Some(2.));
+}
+
+#[test]
+fn arc_length() {
+  let spline = Spline::from_keys(vec![
+    Key::new(0., V2::new(0., 0.), Interpolation::Linear),
+    Key::new(1., V2::new(3., 4.), Interpolation::Linear),
+    Key::new(5., V2::new(3., 9.), Interpolation::Linear)
+  ]);
+  let table = ArcLengthTable::new(&spline, 8);
+
+  assert!((table.length() - 10.).abs() < 1e-4);
+  assert!((table.time_at_distance(5.).unwrap() - 1.).abs() < 1e-4);
+  assert!((table.time_at_distance(7.5).unwrap() - 3.).abs() < 1e-4);
+  assert_eq!(table.time_at_distance(11.), None);
+
+  let p = table.sample_at_distance(&spline, 10.).unwrap();
+  assert!((p.x - 3.).abs() < 1e-4 && (p.y - 9.).abs() < 1e-4);
+
+  // only the middle segment of a Catmull-Rom path can be sampled, up to its very end
+  let path = Spline::from_keys((0..4).map(|i| Key::new(i as f32, V2::new(i as f32, 0.), Interpolation::CatmullRom)).collect());
+  let table = ArcLengthTable::new(&path, 8);
+
+  assert!((table.length() - 1.).abs() < 1e-4);
+  assert!((table.distance_at_time(2.).unwrap() - 1.).abs() < 1e-4);
+  assert!((table.time_at_distance(table.length()).unwrap() - 2.).abs() < 1e-4);
+  assert_eq!(table.distance_at_time(0.5), None);
+
+  let p = table.sample_at_distance(&path, table.length()).unwrap();
+  assert!((p.x - 2.).abs() < 1e-4 && p.y.abs() < 1e-4);
+}
+
+#[test]
+fn sampler() {
+  let spline = Spline::from_keys((0..100).map(|i| Key::new(i as f32, i as f32 * 2., Interpolation::Linear)).collect());
+  let mut sampler = SplineSampler::new(&spline);
+
+  for i in 0..990 {
+    let t = i as f32 * 0.1;
+    assert_eq!(sampler.sample(t), spline.sample(t));
+  }
+
+  assert_eq!(sampler.sample(50.5), Some(101.));
+  assert_eq!(sampler.sample(2.5), Some(5.));
+  assert_eq!(sampler.sample(99.), None);
+}
+
+#[test]
+fn edit_keys() {
+  let mut spline = Spline::from_keys(vec![
+    Key::new(0., 0., Interpolation::Linear),
+    Key::new(10., 10., Interpolation::Linear)
+  ]);
+
+  assert_eq!(spline.insert_key(Key::new(5., 20., Interpolation::Linear)), Ok(1));
+  assert_eq!(spline.sample(2.5), Some(10.));
+  assert_eq!(spline.move_key(1, 20.), Ok(2));
+  assert_eq!(spline.sample(5.), Some(5.));
+  assert_eq!(spline.move_key(3, 20.), Err(SplineError::OutOfBounds(3)));
+  assert!(spline.insert_key(Key::new(::std::f32::NAN, 0., Interpolation::Linear)).is_err());
+  assert!(spline.retime(|t| t * 2.).is_ok());
+  assert_eq!(spline.keys().last().map(|k| k.t), Some(40.));
+  assert_eq!(spline.remove_key(0).map(|k| k.value), Ok(0.));
+  assert_eq!(spline.validate(), Ok(()));
+
+  spline.insert_key(Key::new(0., 0., Interpolation::CatmullRom)).unwrap();
+  assert_eq!(spline.validate(), Err(SplineError::MissingNeighbours(0)));
+}
+
+#[test]
+fn retime_reversed() {
+  let spline = Spline::from_keys(vec![
+    Key::new_with_handles(0., 0., Interpolation::Bezier, None, Some(Handle::new(3., 5.))),
+    Key::new_with_handles(10., 10., Interpolation::Linear, Some(Handle::new(7., 2.)), None)
+  ]);
+
+  let mut reversed = spline.clone();
+  assert_eq!(reversed.retime(|t| 10. - t), Ok(()));
+  assert_eq!(reversed.keys()[0].value, 10.);
+
+  for i in 1..10 {
+    let t = i as f32;
+    assert!((reversed.sample(10. - t).unwrap() - spline.sample(t).unwrap()).abs() < 1e-4);
+  }
+
+  let mut folded = spline.clone();
+  folded.insert_key(Key::new(5., 3., Interpolation::Linear)).unwrap();
+  assert_eq!(folded.retime(|t| (t - 5.).abs()), Err(SplineError::NonMonotonicMap));
+  assert_eq!(folded.keys()[2].t, 10.);
+}
+
+// Directory in which tests can write files.
+fn scratch_dir(name: &str) -> PathBuf {
+  let dir = std::env::temp_dir().join("spectra-tests").join(name);
+  fs::create_dir_all(&dir).unwrap();
+  dir
+}
+
+// Save a spline to the store’s root, load it back through the store and compare.
+fn spline_round_trip<T>(store: &mut Store, root: &PathBuf, name: &str, keys: Vec<Key<T>>)
+    where T: 'static + Copy + Debug + PartialEq + SplineDeserializerAdapter + SplineSerializerAdapter {
+  let mut spline = Spline::from_keys(keys);
+  spline.set_extrapolation(Some(Extrapolation::Constant), Some(Extrapolation::Repeat));
+  spline.save(root.join(name)).unwrap();
+
+  let loaded = store.get(&SplineKey::<T>::new(name)).unwrap();
+  let loaded = loaded.borrow();
+
+  assert_eq!(loaded.pre_extrapolation(), Some(Extrapolation::Constant));
+  assert_eq!(loaded.post_extrapolation(), Some(Extrapolation::Repeat));
+  assert_eq!(loaded.keys().iter().map(|k| (k.t, k.value)).collect::<Vec<_>>(),
+             spline.keys().iter().map(|k| (k.t, k.value)).collect::<Vec<_>>());
+}
+
+#[test]
+fn save_round_trip() {
+  let root = scratch_dir("save_round_trip");
+  let mut store = Store::new(&root).unwrap();
+  let linear = Interpolation::Linear;
+
+  spline_round_trip(&mut store, &root, "f32.json", vec![Key::new(0., 1.5, linear), Key::new(1., -2., linear)]);
+  spline_round_trip(&mut store, &root, "v2.json", vec![Key::new(0., V2::new(1., 2.), linear), Key::new(1., V2::new(3., 4.), linear)]);
+  spline_round_trip(&mut store, &root, "v3.json", vec![Key::new(0., V3::new(1., 2., 3.), linear), Key::new(2., V3::new(0., 0., 1.), linear)]);
+  spline_round_trip(&mut store, &root, "v4.json", vec![Key::new(0., V4::new(1., 2., 3., 4.), linear), Key::new(1., V4::new(0., 1., 0., 1.), linear)]);
+  spline_round_trip(&mut store, &root, "quat.json", vec![
+    Key::new(0., Quaternion::new(1., 0., 0., 0.), linear),
+    Key::new(1., Quaternion::new(0., 0., 0., 1.), linear)
+  ]);
+  spline_round_trip(&mut store, &root, "scale.json", vec![Key::new(0., Scale::new(1., 1., 1.), linear), Key::new(1., Scale::new(2., 0.5, 1.), linear)]);
+
+  // edit a resource in the store and save it back
+  let key = SplineKey::<f32>::new("f32.json");
+  store.get(&key).unwrap().borrow_mut().insert_key(Key::new(0.5, 8., linear)).unwrap();
+  assert_eq!(store.save(&key), Ok(()));
+
+  let mut fresh = Store::new(&root).unwrap();
+  assert_eq!(fresh.get(&key).unwrap().borrow().sample(0.5), Some(8.));
+}
+
+#[test]
+fn save_errors() {
+  let root = scratch_dir("save_errors");
+  let store = Store::new(&root).unwrap();
+  let spline = Spline::from_keys(vec![Key::new(0., 1., Interpolation::Linear)]);
+  let unwritable = root.join("missing").join("spline.json");
+
+  assert_eq!(spline.save(&unwritable), Err(SaveError::CannotCreate(unwritable.clone())));
+  assert_eq!(store.save(&SplineKey::<f32>::new("absent.json")), Err(SaveError::NotInStore(root.canonicalize().unwrap().join("absent.json"))));
+}
+
+#[test]
+fn tcb() {
+  let values = [0., 3., 1., 4., 2.];
+  let tcb = Interpolation::KochanekBartels { tension: 0., continuity: 0., bias: 0. };
+  let catmull_rom = Spline::from_keys(values.iter().enumerate().map(|(i, &v)| Key::new(i as f32, v, Interpolation::CatmullRom)).collect());
+  let kochanek_bartels = Spline::from_keys(values.iter().enumerate().map(|(i, &v)| Key::new(i as f32, v, tcb)).collect());
+
+  for i in 10..30 {
+    let t = i as f32 * 0.1;
+    assert!((catmull_rom.sample(t).unwrap() - kochanek_bartels.sample(t).unwrap()).abs() < 1e-4);
+  }
+
+  // full tension gives null tangents at the keys
+  let tense = Spline::from_keys(values.iter().enumerate().map(|(i, &v)| {
+    Key::new(i as f32, v, Interpolation::KochanekBartels { tension: 1., continuity: 0., bias: 0. })
+  }).collect());
+
+  assert!(tense.sample_derivative(1.).unwrap().abs() < 1e-4);
+  assert_eq!(tense.sample(0.5), None);
+}
+
+#[test]
+fn tcb_uneven_keys() {
+  let times = [0., 1., 3., 3.5, 6.];
+  let values = [0., 3., 1., 4., 2.];
+  let tcb = Interpolation::KochanekBartels { tension: 0., continuity: 0., bias: 0. };
+  let catmull_rom = Spline::from_keys(times.iter().zip(&values).map(|(&t, &v)| Key::new(t, v, Interpolation::CatmullRom)).collect());
+  let kochanek_bartels = Spline::from_keys(times.iter().zip(&values).map(|(&t, &v)| Key::new(t, v, tcb)).collect());
+
+  // the speed of TCB is continuous across keys
+  let before = kochanek_bartels.sample_derivative(3. - 1e-5).unwrap();
+  let after = kochanek_bartels.sample_derivative(3. + 1e-5).unwrap();
+  assert!((before - after).abs() < 1e-2);
+
+  // Catmull-Rom doesn’t scale its tangents, so both differ as soon as keys are unevenly spaced
+  assert!((catmull_rom.sample(1.5).unwrap() - kochanek_bartels.sample(1.5).unwrap()).abs() > 1e-2);
+}
+
+#[test]
+fn monotone_cubic() {
+  let spline = Spline::from_keys(vec![
+    Key::new(0., 0., Interpolation::MonotoneCubic),
+    Key::new(1., 1., Interpolation::MonotoneCubic),
+    Key::new(1.5, 1., Interpolation::MonotoneCubic),
+    Key::new(4., 0., Interpolation::MonotoneCubic)
+  ]);
+
+  for i in 0..400 {
+    let v = spline.sample(i as f32 * 0.01).unwrap();
+    assert!(v >= 0. && v <= 1., "overshoot: {}", v);
+  }
+
+  assert_eq!(spline.sample(1.25), Some(1.));
+  assert_eq!(spline.sample_derivative(1.), Some(0.));
+}
+
+#[test]
+fn easing() {
+  let easings = [
+    Easing::QuadIn, Easing::QuadOut, Easing::QuadInOut,
+    Easing::CubicIn, Easing::CubicOut, Easing::CubicInOut,
+    Easing::ExpoIn, Easing::ExpoOut, Easing::ExpoInOut,
+    Easing::BackIn, Easing::BackOut, Easing::BackInOut,
+    Easing::ElasticIn, Easing::ElasticOut, Easing::ElasticInOut,
+    Easing::BounceIn, Easing::BounceOut, Easing::BounceInOut
+  ];
+
+  for easing in &easings {
+    assert!(easing.ease(0.).abs() < 1e-2, "{:?}", easing);
+    assert!((easing.ease(1.) - 1.).abs() < 1e-2, "{:?}", easing);
+  }
+
+  let spline = Spline::from_keys(vec![
+    Key::new(0., 0., Interpolation::Ease(Easing::QuadIn)),
+    Key::new(10., 100., Interpolation::Linear)
+  ]);
+
+  assert_eq!(spline.sample(5.), Some(25.));
+  assert_eq!(spline.sample_derivative(5.), Some(10.));
+}
+
+#[test]
+fn simplify_spline() {
+  let line = Spline::from_keys((0..1000).map(|i| Key::new(i as f32, i as f32 * 0.5, Interpolation::Linear)).collect());
+  let simplified = simplify(&line, Interpolation::Linear, 1e-3);
+
+  assert_eq!(simplified.keys().len(), 2);
+
+  let sine = Spline::from_keys((0..1000).map(|i| {
+    let t = i as f32 * 0.01;
+    Key::new(t, t.sin(), Interpolation::Linear)
+  }).collect());
+  let simplified = simplify(&sine, Interpolation::MonotoneCubic, 1e-2);
+
+  assert!(simplified.keys().len() < 100);
+
+  for i in 0..999 {
+    let t = i as f32 * 0.01;
+    assert!((simplified.sample(t).unwrap() - sine.sample(t).unwrap()).abs() <= 1e-2);
+  }
+
+  // the simplified spline can be sampled wherever the original one can
+  let curve = Spline::from_keys((0..100).map(|i| Key::new(i as f32, (i as f32 * 0.1).sin(), Interpolation::CatmullRom)).collect());
+  let simplified = simplify(&curve, Interpolation::CatmullRom, 1e-2);
+
+  assert!(simplified.keys().len() < 100);
+
+  for i in 1..98 {
+    let t = i as f32;
+    assert!((simplified.sample(t).unwrap() - curve.sample(t).unwrap()).abs() <= 1e-2);
+    assert!(simplified.sample(t + 0.5).is_some());
+  }
+
+  // Bézier handles are fit to the original spline
+  let linear = simplify(&sine, Interpolation::Linear, 1e-3);
+  let bezier = simplify(&sine, Interpolation::Bezier, 1e-3);
+
+  assert!(bezier.keys().len() < linear.keys().len());
+  assert!(bezier.keys()[0].out_handle.is_some() && bezier.keys()[1].in_handle.is_some());
+
+  for i in 0..9990 {
+    let t = i as f32 * 0.001;
+    assert!((bezier.sample(t).unwrap() - sine.sample(t).unwrap()).abs() <= 2e-3);
+  }
+
+  // TCB keys keep their own parameters
+  let tcb = Spline::from_keys((0..20).map(|i| {
+    Key::new(i as f32, (i as f32 * 0.3).sin(), Interpolation::KochanekBartels { tension: 0.5, continuity: 0., bias: 0. })
+  }).collect());
+  let simplified = simplify(&tcb, Interpolation::KochanekBartels { tension: 0., continuity: 0., bias: 0. }, 1e-2);
+
+  for key in simplified.keys() {
+    match key.interpolation {
+      Interpolation::KochanekBartels { tension, .. } => assert_eq!(tension, 0.5),
+      _ => panic!("the keys should still be TCB keys")
+    }
+  }
+}
+
+#[test]
+fn bake() {
+  let spline = Spline::from_keys(vec![
+    Key::new(0., V2::new(0., 0.), Interpolation::Linear),
+    Key::new(1., V2::new(1., 2.), Interpolation::Linear)
+  ]);
+  let baked = BakedSpline::new(&spline, 0., 1., 4.);
+  let layout = baked.layout();
+
+  assert_eq!(layout.channels, 2);
+  assert_eq!(layout.samples, 5);
+  assert_eq!(baked.data(), &[0., 0., 0.25, 0.5, 0.5, 1., 0.75, 1.5, 1., 2.]);
+  assert_eq!(baked.sample(0.125), Some(V2::new(0.125, 0.25)));
+  assert_eq!(baked.sample(1.), Some(V2::new(1., 2.)));
+  assert_eq!(baked.sample(1.5), None);
+}
+
+#[test]
+fn animation_clip() {
+  let mut clip = AnimationClip::new();
+
+  clip.insert("camera.fov", Spline::from_keys(vec![
+    Key::new(0., 90., Interpolation::Linear),
+    Key::new(1., 45., Interpolation::Linear)
+  ]));
+
+  clip.insert("camera.position", Spline::from_keys(vec![
+    Key::new(0., V2::new(0., 0.), Interpolation::Linear),
+    Key::new(1., V2::new(2., 4.), Interpolation::Linear)
+  ]));
+
+  assert_eq!(clip.sample::<f32>("camera.fov", 0.5), Some(67.5));
+  assert_eq!(clip.sample("camera.position", 0.5), Some(V2::new(1., 2.)));
+  assert!(clip.spline::<V2<f32>>("camera.fov").is_none());
+  assert!(clip.spline::<f32>("light0.color").is_none());
+}
+
+#[test]
+fn animation_clip_file() {
+  let root = scratch_dir("animation_clip_file");
+  let json = r#"{
+    "camera.fov": { "type": "f32", "spline": [{ "t": 0, "value": 90 }, { "t": 1, "value": 45 }] },
+    "camera.target": { "type": "v2", "spline": [{ "t": 0, "value": [0, 0] }, { "t": 2, "value": [2, 4] }] },
+    "light0.color": {
+      "type": "v3",
+      "spline": {
+        "keys": [{ "t": 0, "value": [1, 0, 0] }, { "t": 1, "value": [0, 0, 1] }],
+        "post": "repeat"
+      }
+    },
+    "cube.scale": { "type": "scale", "spline": [{ "t": 0, "value": [1, 1, 1], "interpolation": { "step": 1 } }, { "t": 1, "value": [2, 2, 2] }] }
+  }"#;
+  File::create(root.join("shot.json")).unwrap().write_all(json.as_bytes()).unwrap();
+
+  let mut store = Store::new(&root).unwrap();
+  let clip = store.get(&AnimationClipKey("shot.json".to_owned())).unwrap();
+  let clip = clip.borrow();
+
+  assert_eq!(clip.channels().count(), 4);
+  assert_eq!(clip.sample::<f32>("camera.fov", 0.5), Some(67.5));
+  assert_eq!(clip.sample("camera.target", 1.), Some(V2::new(1., 2.)));
+  assert_eq!(clip.sample("light0.color", 1.5), Some(V3::new(0.5, 0., 0.5)));
+  assert_eq!(clip.sample("cube.scale", 0.5), Some(Scale::new(1., 1., 1.)));
+  assert!(clip.spline::<V3<f32>>("camera.fov").is_none());
+}
+
+#[test]
+fn discrete() {
+  let captions = DiscreteSpline::from_keys(vec![
+    Key::new(10., "greetings".to_owned(), Interpolation::Step(0.5)),
+    Key::new(0., "intro".to_owned(), Interpolation::Step(1.)),
+    Key::new(20., "credits".to_owned(), Interpolation::Step(1.))
+  ]);
+
+  assert_eq!(captions.sample(-1.), None);
+  assert_eq!(captions.sample(0.).map(String::as_str), Some("intro"));
+  assert_eq!(captions.sample(9.).map(String::as_str), Some("intro"));
+  assert_eq!(captions.sample(12.).map(String::as_str), Some("greetings"));
+  assert_eq!(captions.sample(15.).map(String::as_str), Some("credits"));
+  assert_eq!(captions.sample(100.).map(String::as_str), Some("credits"));
+
+  // discrete splines share the file format and the key type of splines
+  let root = scratch_dir("discrete");
+  captions.save(root.join("captions.json")).unwrap();
+
+  let mut store = Store::new(&root).unwrap();
+  let loaded = store.get(&DiscreteSplineKey::<String>::new("captions.json")).unwrap();
+  assert_eq!(loaded.borrow().sample(12.).map(String::as_str), Some("greetings"));
+}
+
+#[test]
+fn transform_spline() {
+  let position = Spline::from_keys(vec![
+    Key::new(0., V3::new(0., 0., 0.), Interpolation::Linear),
+    Key::new(2., V3::new(4., 0., 0.), Interpolation::Linear)
+  ]);
+  let scale = Spline::from_keys(vec![Key::new(0., Scale::uni(2.), Interpolation::Linear)]);
+  let spline = TransformSpline::new(position, Spline::from_keys(Vec::new()), scale);
+
+  let pose = spline.sample(1.);
+  assert_eq!(pose.position, V3::new(2., 0., 0.));
+  assert_eq!(pose.orientation, Quaternion::new(1., 0., 0., 0.));
+  assert_eq!(pose.scale, Scale::uni(2.));
+
+  // held after the last key
+  assert_eq!(spline.sample(10.).position, V3::new(4., 0., 0.));
+
+  let m: [[f32; 4]; 4] = spline.sample_transform(1.).into();
+  assert_eq!(m, [[2., 0., 0., 0.], [0., 2., 0., 0.], [0., 0., 2., 0.], [2., 0., 0., 1.]]);
+
+  // the first and last segments of a Catmull-Rom spline are clamped to the closest segment that
+  // can be sampled
+  let catmull_rom = Spline::from_keys((0..5).map(|i| Key::new(i as f32, V3::new(i as f32, 0., 0.), Interpolation::CatmullRom)).collect());
+  let spline = TransformSpline::new(catmull_rom, Spline::from_keys(Vec::new()), Spline::from_keys(Vec::new()));
+
+  assert_eq!(spline.sample(0.5).position, V3::new(1., 0., 0.));
+  assert_eq!(spline.sample(3.5).position, V3::new(3., 0., 0.));
+  assert_eq!(spline.sample(10.).position, V3::new(4., 0., 0.));
+}
+
+#[test]
+fn noise() {
+  for &kind in &[NoiseKind::Value, NoiseKind::Perlin, NoiseKind::Simplex] {
+    let noise = Noise::new(kind, 42, 3., 4);
+    let other_seed = Noise::new(kind, 43, 3., 4);
+    let mut differs = false;
+
+    for i in 0..100 {
+      let x = i as f32 * 0.137;
+      let n = noise.sample1(x);
+
+      assert!(n >= -1.5 && n <= 1.5);
+      assert_eq!(n, noise.sample1(x));
+      assert_eq!(noise.sample2(V2::new(x, 1.)), noise.sample2(V2::new(x, 1.)));
+      assert_eq!(noise.sample3(V3::new(x, 1., 2.)), noise.sample3(V3::new(x, 1., 2.)));
+
+      differs = differs || n != other_seed.sample1(x);
+    }
+
+    assert!(differs);
+  }
+
+  let spline = Spline::from_keys(vec![
+    Key::new(0., V3::new(0., 0., 0.), Interpolation::Linear),
+    Key::new(10., V3::new(10., 0., 0.), Interpolation::Linear)
+  ]);
+  let amplitude = Spline::from_keys(vec![
+    Key::new(0., 0., Interpolation::Linear),
+    Key::new(5., 1., Interpolation::Linear)
+  ]);
+  let shake = NoiseModifier::new(spline, Noise::new(NoiseKind::Simplex, 0, 2., 2), amplitude);
+
+  assert_eq!(shake.sample(0.), Some(V3::new(0., 0., 0.)));
+  assert_eq!(shake.sample(10.), None);
+
+  let shaken = shake.sample(7.3).unwrap();
+  assert!((shaken - V3::new(7.3, 0., 0.)).x.abs() <= 1.5);
+  assert!(shaken != V3::new(7.3, 0., 0.));
+
+  // modifiers with adjacent seeds don’t share channels
+  let still = Spline::from_keys(vec![Key::new(0., V2::new(0., 0.), Interpolation::Linear), Key::new(10., V2::new(0., 0.), Interpolation::Linear)]);
+  let full = Spline::from_keys(vec![Key::new(0., 1., Interpolation::Linear)]);
+  let first = NoiseModifier::new(still.clone(), Noise::new(NoiseKind::Value, 0, 2., 1), full.clone());
+  let second = NoiseModifier::new(still, Noise::new(NoiseKind::Value, 1, 2., 1), full);
+
+  assert!((0..20).any(|i| {
+    let t = i as f32 * 0.37;
+    first.sample(t).unwrap().y != second.sample(t).unwrap().x
+  }));
+
+  // an amplitude spline that cannot be sampled near its ends is clamped instead of panicking
+  let mut catmull_rom = first.clone();
+  catmull_rom.amplitude = Spline::from_keys((0..4).map(|i| Key::new(i as f32, 1., Interpolation::CatmullRom)).collect());
+  assert_eq!(catmull_rom.sample_amplitude(0.5), 1.);
+  assert_eq!(catmull_rom.sample_amplitude(2.5), 1.);
+}
+
+#[test]
+fn spline_queries() {
+  let spline = Spline::from_keys(vec![
+    Key::new(0., 0., Interpolation::Linear),
+    Key::new(1., 2., Interpolation::Linear),
+    Key::new(2., 0., Interpolation::Linear),
+    Key::new(3., 2., Interpolation::Linear)
+  ]);
+
+  let crossings = spline.crossings(1., 0., 3.);
+  let expected = [
+    (0.5, CrossingDirection::Rising),
+    (1.5, CrossingDirection::Falling),
+    (2.5, CrossingDirection::Rising)
+  ];
+
+  assert_eq!(crossings.len(), expected.len());
+
+  for (crossing, &(t, direction)) in crossings.iter().zip(&expected) {
+    assert!((crossing.t - t).abs() < 1e-4);
+    assert_eq!(crossing.direction, direction);
+  }
+
+  let extrema = spline.extrema(0., 3.);
+  assert_eq!(extrema.len(), 2);
+  assert_eq!(extrema[0].kind, ExtremumKind::Maximum);
+  assert!((extrema[0].t - 1.).abs() < 1e-3 && (extrema[0].value - 2.).abs() < 1e-3);
+  assert_eq!(extrema[1].kind, ExtremumKind::Minimum);
+  assert!((extrema[1].t - 2.).abs() < 1e-3 && extrema[1].value.abs() < 1e-3);
+
+  assert_eq!(spline.value_range(0., 3.), Some((0., 2.)));
+  assert_eq!(spline.value_range(5., 6.), None);
+
+  let (min, max) = spline.value_range(0.5, 1.5).unwrap();
+  assert!((min - 1.).abs() < 1e-4 && (max - 2.).abs() < 1e-3);
+
+  // without extrapolation, only the keys’ range is scanned
+  assert_eq!(spline.value_range(-1e30, 1e30), Some((0., 2.)));
+  assert_eq!(spline.crossings(1., -1e30, 1e30).len(), expected.len());
+
+  // repeating extrapolation far away from the keys
+  let mut repeating = spline.clone();
+  repeating.set_extrapolation(None, Some(Extrapolation::Repeat));
+  let (min, max) = repeating.value_range(0., 1e30).unwrap();
+  assert!(min >= 0. && max <= 2.);
+}
+
+#[test]
+fn blend() {
+  let take_a = Spline::from_keys(vec![
+    Key::new(0., 0., Interpolation::Linear),
+    Key::new(10., 0., Interpolation::Linear)
+  ]);
+  let take_b = Spline::from_keys(vec![
+    Key::new(0., 10., Interpolation::Linear),
+    Key::new(10., 10., Interpolation::Linear)
+  ]);
+  let fade_in = Spline::from_keys(vec![
+    Key::new(2., 0., Interpolation::Linear),
+    Key::new(6., 1., Interpolation::Linear)
+  ]);
+  let wobble = Spline::from_keys(vec![
+    Key::new(0., 1., Interpolation::Linear),
+    Key::new(20., 1., Interpolation::Linear)
+  ]);
+
+  let mut crossfade = Blend::new(vec![
+    Layer::new(take_a, Spline::from_keys(Vec::new()), BlendMode::Override),
+    Layer::new(take_b, fade_in, BlendMode::Override)
+  ]);
+
+  assert_eq!(crossfade.sample(0.), Some(0.));
+  assert_eq!(crossfade.sample(4.), Some(5.));
+  assert_eq!(crossfade.sample(8.), Some(10.));
+  assert_eq!(crossfade.sample(15.), None);
+
+  // symmetric weights don’t crossfade, as weights are not normalized
+  let take = |v: f32| Spline::from_keys(vec![Key::new(0., v, Interpolation::Linear), Key::new(10., v, Interpolation::Linear)]);
+  let ramp = |from: f32, to: f32| Spline::from_keys(vec![Key::new(0., from, Interpolation::Linear), Key::new(10., to, Interpolation::Linear)]);
+
+  let symmetric = Blend::new(vec![
+    Layer::new(take(10.), ramp(1., 0.), BlendMode::Override),
+    Layer::new(take(20.), ramp(0., 1.), BlendMode::Override)
+  ]);
+  assert_eq!(symmetric.sample(0.), Some(10.));
+  assert_eq!(symmetric.sample(5.), Some(0.25 * 10. + 0.5 * 20.));
+
+  let held_bottom = Blend::new(vec![
+    Layer::new(take(10.), Spline::from_keys(Vec::new()), BlendMode::Override),
+    Layer::new(take(20.), ramp(0., 1.), BlendMode::Override)
+  ]);
+  assert_eq!(held_bottom.sample(5.), Some(15.));
+
+  let half = Spline::from_keys(vec![Key::new(0., 0.5, Interpolation::Step(1.))]);
+  crossfade.push(Layer::new(wobble, half, BlendMode::Additive));
+
+  assert_eq!(crossfade.sample(4.), Some(5.5));
+  assert_eq!(crossfade.sample(15.), Some(0.5));
+
+  // Catmull-Rom weights hold their nearest sampleable value in their first and last segments
+  let catmull_rom: Layer<f32> = Layer::new(Spline::from_keys(Vec::new()), Spline::from_keys(vec![
+    Key::new(0., 0., Interpolation::CatmullRom),
+    Key::new(1., 0.25, Interpolation::CatmullRom),
+    Key::new(2., 0.75, Interpolation::CatmullRom),
+    Key::new(3., 1., Interpolation::CatmullRom)
+  ]), BlendMode::Override);
+
+  assert_eq!(catmull_rom.sample_weight(0.5), 0.25);
+  assert_eq!(catmull_rom.sample_weight(2.5), 0.75);
+  assert!((catmull_rom.sample_weight(1.5) - 0.5).abs() < 1e-5);
+
+  let unsampleable: Layer<f32> = Layer::new(Spline::from_keys(Vec::new()), Spline::from_keys(vec![
+    Key::new(0., 0.5, Interpolation::CatmullRom),
+    Key::new(1., 0.5, Interpolation::CatmullRom)
+  ]), BlendMode::Additive);
+
+  assert_eq!(unsampleable.sample_weight(0.5), 0.);
+
+  // blending rotations
+  let half_turn = Quaternion::new(0., 0., 0., 1.);
+  let rotations = Blend::new(vec![
+    Layer::new(Spline::from_keys(vec![
+      Key::new(0., half_turn, Interpolation::Linear),
+      Key::new(1., half_turn, Interpolation::Linear)
+    ]), Spline::from_keys(vec![Key::new(0., 0.5, Interpolation::Linear)]), BlendMode::Override)
+  ]);
+
+  let q = rotations.sample(0.5).unwrap();
+  let s = consts::FRAC_PI_4.cos();
+  assert!((q.s - s).abs() < 1e-5 && (q.v.z - s).abs() < 1e-5);
+  assert!(q.v.x.abs() < 1e-5 && q.v.y.abs() < 1e-5);
+}
+
+#[test]
+fn glsl_codegen() {
+  let mut spline = Spline::from_keys(vec![
+    Key::new(0., 0., Interpolation::Linear),
+    Key::new(1., 1., Interpolation::Ease(Easing::BounceOut)),
+    Key::new(2., 0.5, Interpolation::Step(1.)),
+    Key::new(3., 0., Interpolation::Linear)
+  ]);
+  spline.set_extrapolation(None, Some(Extrapolation::Repeat));
+
+  let src = spline_to_glsl(&spline, "fade").unwrap();
+  assert!(src.contains("float fade(float t) {"));
+  assert!(src.contains("float fade_closed(float t) {"));
+  assert!(src.contains("float fade_bounce_in(float x) {"));
+  assert!(src.contains("return fade_closed(t - c * 3.0);"));
+  assert!(!src.contains("fade_hermite"));
+  assert!(spline_to_module(&spline, "fade").is_ok());
+
+  let constant = Spline::from_keys(vec![Key::new(0., V2::new(1., 2.), Interpolation::Linear)]);
+  assert_eq!(spline_to_glsl(&constant, "offset").unwrap(), "vec2 offset(float t) {\n  return vec2(1.0, 2.0);\n}\n");
+
+  let empty: Spline<f32> = Spline::from_keys(Vec::new());
+  assert_eq!(spline_to_glsl(&empty, "nothing"), Err(CodegenError::NoKey));
+
+  let catmull_rom = Spline::from_keys(vec![
+    Key::new(0., 0., Interpolation::CatmullRom),
+    Key::new(1., 1., Interpolation::CatmullRom)
+  ]);
+  assert_eq!(spline_to_glsl(&catmull_rom, "broken"), Err(CodegenError::InvalidSpline(SplineError::MissingNeighbours(0))));
+
+  // Hermite, Bézier and easing helpers must parse as well
+  let helpers = Spline::from_keys(vec![
+    Key::new(0., 0., Interpolation::Linear),
+    Key::new(1., 1., Interpolation::CatmullRom),
+    Key::new_with_handles(2., 0.5, Interpolation::Bezier, None, Some(Handle::new(2.25, 1.))),
+    Key::new_with_handles(3., 2., Interpolation::KochanekBartels { tension: 0.5, continuity: 0., bias: 0. },
+                          Some(Handle::new(2.75, 2.)), None),
+    Key::new(4., 1., Interpolation::Ease(Easing::ElasticInOut)),
+    Key::new(5., 0., Interpolation::Ease(Easing::BounceOut)),
+    Key::new(6., 1., Interpolation::Linear)
+  ]);
+
+  let src = spline_to_glsl(&helpers, "curve").unwrap();
+  assert!(src.contains("curve_hermite(") && src.contains("curve_bezier_time("));
+  assert!(src.contains("curve_elastic_in(") && src.contains("curve_bounce_in("));
+  spline_to_module(&helpers, "curve").unwrap();
+
+  // GLSL has no literal for NaN and infinities
+  let nan = Spline::from_keys(vec![
+    Key::new(0., 0., Interpolation::Linear),
+    Key::new(1., ::std::f32::NAN, Interpolation::Linear)
+  ]);
+  assert_eq!(spline_to_glsl(&nan, "nan"), Err(CodegenError::NonFiniteKey(1)));
+
+  let infinite_handle = Spline::from_keys(vec![
+    Key::new_with_handles(0., 0., Interpolation::Bezier, None, Some(Handle::new(0.5, ::std::f32::INFINITY))),
+    Key::new(1., 1., Interpolation::Linear)
+  ]);
+  assert_eq!(spline_to_glsl(&infinite_handle, "infinite"), Err(CodegenError::NonFiniteKey(0)));
+
+  // repeating a spline which keys are all at the same time holds its value
+  let mut instant = Spline::from_keys(vec![
+    Key::new(1., 2., Interpolation::Linear),
+    Key::new(1., 2., Interpolation::Linear)
+  ]);
+  instant.set_extrapolation(Some(Extrapolation::Repeat), Some(Extrapolation::OffsetRepeat));
+
+  let src = spline_to_glsl(&instant, "instant").unwrap();
+  assert!(!src.contains("floor"));
+  spline_to_module(&instant, "instant").unwrap();
+}
+
+#[test]
+fn cut_context() {
+  let clip = Clip::new(|t| t);
+  let contextual = Clip::with_context(|ctx: CutContext| ctx.progress * 100. + ctx.track as f64);
+
+  let mut timeline = Timeline::new();
+  timeline.add_track(Track::from(&[Cut::new(2., 6., 10., &clip)][..]));
+  timeline.add_track(Track::from(&[Cut::new(0., 8., 20., &contextual)][..]));
+
+  match timeline.play(11.) {
+    Played::Resolved(t) => assert_eq!(t, 3.),
+    _ => panic!("the first cut should be active")
+  }
+
+  match timeline.play(22.) {
+    Played::Resolved(x) => assert_eq!(x, 26.),
+    _ => panic!("the second cut should be active")
+  }
+
+  let ctx = Cut::new(2., 6., 10., &clip).context(13., 0);
+  assert_eq!(ctx, CutContext { global_time: 13., local_time: 5., progress: 0.75, dur: 4., track: 0 });
+}
+
+#[test]
+fn timeline_manifest_round_trip() {
+  let intro = Clip::new(|_| 0);
+  let tunnel = Clip::new(|_| 1);
+  let unnamed = Clip::new(|_| 2);
+
+  let mut mapping = HashMap::new();
+  mapping.insert("intro".to_owned(), &intro);
+  mapping.insert("tunnel".to_owned(), &tunnel);
+
+  let manifest = TimelineManifest {
+    tracks: vec![
+      TrackManifest {
+        cuts: vec![
+          CutManifest { in_time: 0., out_time: 10., inst_time: 0., clip: "intro".to_owned() },
+          CutManifest { in_time: 5., out_time: 20., inst_time: 10., clip: "tunnel".to_owned() }
+        ]
+      },
+      TrackManifest {
+        cuts: vec![CutManifest { in_time: 0., out_time: 2., inst_time: 9., clip: "intro".to_owned() }]
+      }
+    ],
+    overlaps: Vec::new()
+  };
+
+  let mut timeline = Timeline::from_manifest(&manifest, &mapping, &HashMap::new());
+  assert_eq!(timeline.to_manifest(&mapping), manifest);
+
+  // cuts playing clips missing from the mapping are left out
+  timeline.add_track(Track::from(&[Cut::new(0., 1., 30., &unnamed)][..]));
+  let mut expected = manifest.clone();
+  expected.tracks.push(TrackManifest { cuts: Vec::new() });
+  assert_eq!(timeline.to_manifest(&mapping), expected);
+}
+
+#[test]
+fn timeline_manifest_overlaps() {
+  let red = Clip::new(|_| 1.);
+  let blue = Clip::new(|_| 3.);
+  let mix = Fold::new(|nodes: Vec<f64>, params: &FoldParams| {
+    let k = params.get("k").cloned().unwrap_or(0.5) as f64;
+    nodes[0] * (1. - k) + nodes[1] * k
+  });
+
+  let mut mapping = HashMap::new();
+  mapping.insert("red".to_owned(), &red);
+  mapping.insert("blue".to_owned(), &blue);
+
+  let mut folds = HashMap::new();
+  folds.insert("mix".to_owned(), &mix);
+
+  let json = r#"{
+    "tracks": [
+      { "cuts": [{ "in_time": 0, "out_time": 10, "inst_time": 0, "clip": "red" }] },
+      { "cuts": [{ "in_time": 0, "out_time": 10, "inst_time": 5, "clip": "blue" }] }
+    ],
+    "overlaps": [
+      { "inst_time": 5, "dur": 2, "fold": "mix" },
+      { "inst_time": 7, "dur": 3, "fold": "mix", "params": { "k": 0.25 } },
+      { "inst_time": 10, "dur": 5, "fold": "unknown" }
+    ]
+  }"#;
+  let manifest: TimelineManifest = serde_json::from_str(json).unwrap();
+  let timeline = Timeline::from_manifest(&manifest, &mapping, &folds);
+
+  match timeline.play(6.) {
+    Played::Resolved(x) => assert_eq!(x, 2.),
+    _ => panic!("the first overlap should be active")
+  }
+
+  match timeline.play(8.) {
+    Played::Resolved(x) => assert_eq!(x, 1.5),
+    _ => panic!("the second overlap should be active")
+  }
+
+  // the overlap with an unknown fold is dropped
+  let mut expected = manifest.clone();
+  expected.overlaps.pop();
+  assert_eq!(timeline.to_manifest(&mapping), expected);
+}
+
+#[test]
+fn timeline_validation() {
+  let json = r#"{
+    "tracks": [
+      {
+        "cuts": [
+          { "in_time": 0, "out_time": 10, "inst_time": 0, "clip": "intro" },
+          { "in_time": 0, "out_time": 10, "inst_time": 15, "clip": "tunnel" },
+          { "in_time": 4, "out_time": 2, "inst_time": 30, "clip": "intro" }
+        ]
+      },
+      {
+        "cuts": [
+          { "in_time": 0, "out_time": 6, "inst_time": 20, "clip": "outro" },
+          { "in_time": 0, "out_time": 5, "inst_time": 40, "clip": "credits" }
+        ]
+      }
+    ],
+    "overlaps": [
+      { "inst_time": 20, "dur": 2, "fold": "mix" },
+      { "inst_time": 0, "dur": 1, "fold": "wipe" }
+    ]
+  }"#;
+  let manifest: TimelineManifest = serde_json::from_str(json).unwrap();
+
+  let mut clips = HashMap::new();
+  clips.insert("intro".to_owned(), ());
+  clips.insert("tunnel".to_owned(), ());
+  clips.insert("outro".to_owned(), ());
+
+  let mut folds = HashMap::new();
+  folds.insert("mix".to_owned(), ());
+
+  let issues = manifest.validate(&clips, &folds, Some(42.));
+
+  assert_eq!(issues, vec![
+    TimelineIssue::ReversedCut { track: 0, cut: 2 },
+    TimelineIssue::UnknownClip { track: 1, cut: 1, clip: "credits".to_owned() },
+    TimelineIssue::UnknownFold { overlap: 1, fold: "wipe".to_owned() },
+    TimelineIssue::Gap { start: 10., end: 15. },
+    TimelineIssue::UncoveredCuts { start: 22., end: 25. },
+    TimelineIssue::Gap { start: 26., end: 42. }
+  ]);
+
+  // a reversed cut doesn’t make the manifest panic anymore
+  let intro = Clip::new(|_| ());
+  let mut mapping = HashMap::new();
+  mapping.insert("intro".to_owned(), &intro);
+
+  let timeline = Timeline::from_manifest(&manifest, &mapping, &HashMap::new());
+  assert_eq!(timeline.validate(Some(5.)), vec![TimelineIssue::BeyondEnd { track: 0, cut: 0, end: 10. }]);
+
+  // cuts of two tracks touching at an instant are both active there
+  let a = Clip::new(|_| 1);
+  let b = Clip::new(|_| 10);
+
+  let mut touching = Timeline::new();
+  touching.add_track(Track::from(&[Cut::new(0., 10., 0., &a)][..]));
+  touching.add_track(Track::from(&[Cut::new(0., 10., 10., &b)][..]));
+
+  assert_eq!(touching.validate(None), vec![TimelineIssue::UncoveredCuts { start: 10., end: 10. }]);
+
+  match touching.play(10.) {
+    Played::NoOverlap => (),
+    _ => panic!("both cuts should be active without an overlap")
+  }
+
+  touching.add_overlap(Overlap::new(10., 0., |nodes: Vec<i32>| nodes.into_iter().sum()));
+  assert_eq!(touching.validate(None), vec![]);
+
+  match touching.play(10.) {
+    Played::Resolved(x) => assert_eq!(x, 11),
+    _ => panic!("the overlap should fold both cuts")
+  }
+}
+
+#[test]
+fn active_cuts() {
+  let a = Clip::new(|_| 1);
+  let b = Clip::new(|_| 10);
+  let c = Clip::new(|_| 100);
+
+  let mut timeline = Timeline::new();
+  timeline.add_track(Track::from(&[Cut::new(0., 10., 0., &a), Cut::new(0., 10., 20., &a)][..]));
+  timeline.add_track(Track::from(&[Cut::new(0., 5., 8., &b), Cut::new(0., 30., 12., &c)][..]));
+  timeline.add_overlap(Overlap::new(8., 20., |nodes: Vec<i32>| nodes.into_iter().sum()));
+  timeline.add_overlap(Overlap::new(0., 40., |nodes: Vec<i32>| nodes.into_iter().product()));
+
+  let mut cuts = Vec::new();
+  let mut active = |t| {
+    timeline.active_cuts(t, &mut cuts);
+    cuts.iter().map(|&(i, cut)| (i, cut.inst_time)).collect::<Vec<_>>()
+  };
+
+  assert_eq!(active(-1.), vec![]);
+  assert_eq!(active(5.), vec![(0, 0.)]);
+  assert_eq!(active(9.), vec![(0, 0.), (1, 8.)]);
+  assert_eq!(active(12.), vec![(1, 8.), (1, 12.)]);
+  assert_eq!(active(14.), vec![(1, 12.)]);
+  assert_eq!(active(25.), vec![(0, 20.), (1, 12.)]);
+  assert_eq!(active(42.), vec![(1, 12.)]);
+  assert_eq!(active(43.), vec![]);
+
+  // the first overlap added wins
+  match timeline.play(9.) {
+    Played::Resolved(x) => assert_eq!(x, 11),
+    _ => panic!("the first overlap should be used")
+  }
+
+  match timeline.play(30.) {
+    Played::Resolved(x) => assert_eq!(x, 100),
+    _ => panic!("the second overlap should be used")
+  }
+}